@@ -3,121 +3,382 @@ extern crate zilean;
 #[macro_use] extern crate slog;
 #[macro_use] extern crate slog_term;
 extern crate serde_json;
+extern crate regex;
 use slog::Logger as Logger;
+use zilean::bulk::{self, BulkOptions};
+use zilean::cache::ChampionCache as ChampionCache;
 use zilean::database::Database as Database;
-use std::process::Command;
+use zilean::champion::{Champion, Stats};
+use zilean::corpus::Entry as Entry;
+use zilean::registry::{ChampionInfo, ChampionRegistry};
+use zilean::snapshot::{self, ImportMode, SnapshotStats};
+use zilean::store::StoreError as StoreError;
+use serde_json::Value;
+use serde_json::builder::{ArrayBuilder, ObjectBuilder};
+use std::cmp;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::process::{self, Command};
 use std::thread;
 use std::time::Duration;
-use std::sync::Mutex;
+use std::sync::Arc;
 use std::collections::HashMap;
-use nickel::{Nickel, HttpRouter};
+use nickel::{Nickel, HttpRouter, QueryString};
+use regex::Regex;
 
 //Parameters of the server and the script
 const VERSION : &'static str = "0.1";
-const REDIS_URL : &'static str = "redis://127.0.0.1";
+const REDIS_URL : &'static str = "redis://127.0.0.1"; //used when DATABASE_URL isn't set
 const TWITCH_PATH : &'static str = "../twitch/twitch.py";
+const REALM : &'static str = "EUW"; //twitch's default realm
+const STATIC_DATA_PATH : &'static str = "../twitch/dbg_champs.json";
 const SERVER_ADDRESS : &'static str = "127.0.0.1:6767";
 const GEN_LENGTH : u32 = 16u32; //riot value
+const TOP_DEFAULT : u32 = 10;
+const TOP_MAX : u32 = 100;
+const CONNECT_DELAY_MAX : u64 = 60; //seconds between two database connection attempts, at most
+const CACHE_TTL : u64 = 30; //seconds a champion is served from the cache, so that other writers are seen
+
+//Answer statuses, besides 0 : success and 1 : error.
+const STATUS_NOT_FOUND : u32 = 2; //the champion was never stored
+const STATUS_UNAVAILABLE : u32 = 3; //the database failed to answer
+const STATUS_CORRUPT : u32 = 4; //the stored champion can't be read
+const STATUS_CONFLICT : u32 = 5; //other writers kept changing the champion, trying again later may succeed
 
 //Used to format the REST response
 struct Answer {
     status: u32,
     content_type: String,
-    content: String
+    content: Value,
+    champion: Option<Value>
 }
 
 //TODO: Implement serialize trait.
 impl Answer {
     fn serialize(&self) -> Result<String, serde_json::Error> {
-        let obj = serde_json::builder::ObjectBuilder::new()
+        let obj = ObjectBuilder::new()
             .insert("status", self.status.clone())
             .insert("content_type", self.content_type.clone())
-            .insert("content", self.content.clone())
-            .unwrap();
+            .insert("content", self.content.clone());
+        let obj = match self.champion {
+            Some(ref champion) => obj.insert("champion", champion.clone()),
+            None => obj,
+        }.unwrap();
         serde_json::to_string(&obj)
     }
+
+    fn error(content : String) -> Answer {
+        Answer::failure(1, content)
+    }
+
+    fn failure(status : u32, content : String) -> Answer {
+        Answer {
+            status: status,
+            content_type: "err".to_string(),
+            content: Value::String(content),
+            champion: None}
+    }
 }
 fn main() {
     //Root logger, from which we'll instantiate twitch/rest loggers.
     let root = Logger::new_root(o!("version" => VERSION));
     root.set_drain(slog_term::async_stderr());
 
+    //`coordinator export <file>`, `import <file> [--replace]`, `import-bulk <file>` and `migrate` fill, dump or
+    //upgrade the database instead of serving it.
+    let args : Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        let log = root.new(o!("job" => "snapshot"));
+        process::exit(run_command(&args, &log));
+    }
 
     root.info("Starting REST server.", b!("port" => SERVER_ADDRESS));
     let log = root.new(o!("job" => "rest_server"));
 
+    //A single database shared by both threads, so that in memory storage works. With redis, its connection pool
+    //reconnects by itself when the instance restarts.
+    let db = Arc::new(connect_database(&log));
+    log.info("Database connection established.", b!("URL" => db.get_url()));
+
+    //Champions shared by the HTTP workers, kept up to date by the twitch loop. Other coordinators and imports may write
+    //to the same database, so champions are loaded again once they expire.
+    let cache = Arc::new(ChampionCache::with_ttl(Duration::from_secs(CACHE_TTL)));
+
     //We spawn the server thread.
     thread::spawn({
         let log = log.clone();
+        let db = db.clone();
+        let cache = cache.clone();
         move || {
 
-            let db = Mutex::new(Database::new(REDIS_URL.to_string()));
-            log.info("Database connection established.", b!("URL" => REDIS_URL));
+            //Without a registry, every id is accepted and answers don't carry champion names.
+            let registry = Arc::new(load_registry(&db, &log));
 
             let mut server = Nickel::new();
 
             //We react on the requests touching /gen/something
-            server.get("/gen/:id", middleware! { |request, response|
-
-                //By default, the answer status is 1 : error.
-                let mut answer = Answer {
-                    status: 1,
-                    content_type: "err".to_string(),
-                    content: "not yet initialized".to_string()};
-
-                //Checking the validity of the id. (Exists, is a u32)
-                let champion_id = match request.param("id") {
-                    Some(id) => match id.parse::<u32>() {
-                        Ok(id) => {
-                            id
-                        },
+            server.get("/gen/:id", {
+                let db = db.clone();
+                let cache = cache.clone();
+                let registry = registry.clone();
+                let log = log.clone();
+                middleware! { |request, response|
+
+                    //By default, the answer status is 1 : error.
+                    let mut answer = Answer::error("not yet initialized".to_string());
+
+                    //Checking the validity of the id. (Exists, is a u32)
+                    let champion_id = match parse_champion_id(request.param("id")) {
+                        Ok(id) => id,
                         Err(err) => {
-                            answer.status = 1;
-                            answer.content_type = "err".to_string();
-                            answer.content = err.to_string();
-                            log.warn("Invalid id supplied", b!("err" => err.to_string()));
+                            log.warn("Invalid id supplied", b!("err" => err.clone()));
+                            answer.content = Value::String(err);
                             return response.send(format!("{}", answer.serialize().unwrap()))
                         }
-                    },
-                    None => {
-                        answer.status = 1;
-                        answer.content_type = "err".to_string();
-                        answer.content = "No id parameter specified.".to_string();
-                        return response.send(format!("{}", answer.serialize().unwrap()))
+                    };
+                    answer.champion = match find_champion(&registry, champion_id) {
+                        Ok(champion) => champion,
+                        Err(err) => {
+                            answer.content = Value::String(err);
+                            return response.send(format!("{}", answer.serialize().unwrap()))
+                        }
+                    };
+                    let answer = match load_champion(&cache, &db, champion_id) {
+                        Ok(champion) => generate(&champion, answer),
+                        Err(failure) => {
+                            log.warn("Impossible to load the champion", b!("champion_id" => champion_id, "status" => failure.status));
+                            failure
+                        },
+                    };
+                    return response.send(format!("{}", answer.serialize().unwrap()))
+                }
+            });
+
+            //Same as /gen/:id, but using the champion's name, like /gen/by-name/yasuo
+            //A regex, since `:name` would neither match the dots of "Dr. Mundo" nor the quotes of "Kha'Zix".
+            server.get(Regex::new(r"^/gen/by-name/(?P<name>[^/?]+)(\?.*)?$").unwrap(), {
+                let db = db.clone();
+                let cache = cache.clone();
+                let registry = registry.clone();
+                middleware! { |request, response|
+                    let registry = match *registry {
+                        Some(ref registry) => registry,
+                        None => return response.send(format!("{}", Answer::error("champion names are unavailable".to_string()).serialize().unwrap())),
+                    };
+                    let name = percent_decode(request.param("name").unwrap_or(""));
+                    let champion = match registry.find_by_name(&name) {
+                        Ok(champion) => champion,
+                        Err(suggestions) => return response.send(format!("{}", Answer::error(unknown_name(&name, &suggestions)).serialize().unwrap())),
+                    };
+
+                    let mut answer = Answer::error("not yet initialized".to_string());
+                    answer.champion = Some(champion_to_json(champion));
+                    let answer = match load_champion(&cache, &db, champion.id) {
+                        Ok(champion) => generate(&champion, answer),
+                        Err(failure) => failure,
+                    };
+                    return response.send(format!("{}", answer.serialize().unwrap()))
+                }
+            });
+
+            //The most likely nicknames of a champion : /gen/:id/top?k=10&min=1&max=16
+            server.get("/gen/:id/top", {
+                let db = db.clone();
+                let cache = cache.clone();
+                let registry = registry.clone();
+                middleware! { |request, response|
+                    let champion_id = match parse_champion_id(request.param("id")) {
+                        Ok(id) => id,
+                        Err(err) => return response.send(format!("{}", Answer::error(err).serialize().unwrap())),
+                    };
+                    let champion_info = match find_champion(&registry, champion_id) {
+                        Ok(champion) => champion,
+                        Err(err) => return response.send(format!("{}", Answer::error(err).serialize().unwrap())),
+                    };
+                    let query = request.query();
+                    let bounds = parse_query_param(query.get("k"), TOP_DEFAULT)
+                        .and_then(|k| parse_query_param(query.get("min"), 1).map(|min| (k, min)))
+                        .and_then(|(k, min)| parse_query_param(query.get("max"), GEN_LENGTH).map(|max| (k, min, max)));
+                    let (k, min_len, max_len) = match bounds {
+                        Ok((k, _, _)) if k > TOP_MAX => return response.send(format!("{}", Answer::error(format!("k can't be more than {}", TOP_MAX)).serialize().unwrap())),
+                        Ok(bounds) => bounds,
+                        Err(err) => return response.send(format!("{}", Answer::error(err).serialize().unwrap())),
+                    };
+                    let champion = match load_champion(&cache, &db, champion_id) {
+                        Ok(champion) => champion,
+                        Err(failure) => return response.send(format!("{}", failure.serialize().unwrap())),
+                    };
+                    if champion.is_empty() {
+                        return response.send(format!("{}", Answer::error("no nickname was fed to this champion yet".to_string()).serialize().unwrap()))
                     }
-                };
-                //Locking the db in order to access it.
-                //Not necessary, since we just read the values.
-                //I have to figure out why I've done this.
-                let db_lock = match db.lock() {
-                    Ok(db) => db,
-                    Err(err) => {
-                        answer.status = 1;
-                        answer.content_type = "err".to_string();
-                        answer.content = err.to_string();
-                        return response.send(format!("{}", answer.serialize().unwrap()))
+
+                    let nicknames = champion.top(k as usize, min_len, max_len).into_iter().fold(ArrayBuilder::new(), |builder, (nickname, probability)| {
+                        builder.push_object(|obj| obj.insert("nickname", nickname).insert("probability", probability))
+                    }).unwrap();
+                    let answer = Answer {
+                        status: 0,
+                        content_type: "nicknames".to_string(),
+                        content: nicknames,
+                        champion: champion_info};
+                    return response.send(format!("{}", answer.serialize().unwrap()))
+                }
+            });
+
+            //Statistics about the data we have for a champion.
+            server.get("/champions/:id/stats", {
+                let db = db.clone();
+                let cache = cache.clone();
+                let registry = registry.clone();
+                middleware! { |request, response|
+
+                    let champion_id = match parse_champion_id(request.param("id")) {
+                        Ok(id) => id,
+                        Err(err) => return response.send(format!("{}", Answer::error(err).serialize().unwrap())),
+                    };
+                    let champion_info = match find_champion(&registry, champion_id) {
+                        Ok(champion) => champion,
+                        Err(err) => return response.send(format!("{}", Answer::error(err).serialize().unwrap())),
+                    };
+                    let champion = match load_champion(&cache, &db, champion_id) {
+                        Ok(champion) => champion,
+                        Err(failure) => return response.send(format!("{}", failure.serialize().unwrap())),
+                    };
+                    if champion.is_empty() {
+                        return response.send(format!("{}", Answer::error("no nickname was fed to this champion yet".to_string()).serialize().unwrap()))
                     }
-                };
 
-                //Loading the requested champion_id data
-                let champion = db_lock.load(champion_id);
+                    let answer = Answer {
+                        status: 0,
+                        content_type: "stats".to_string(),
+                        content: stats_to_json(&champion.stats()),
+                        champion: champion_info};
+                    return response.send(format!("{}", answer.serialize().unwrap()))
+                }
+            });
 
-                //Trying to generate a nickname
-                match champion.gen(GEN_LENGTH) {
-                    Some(nickname) => {
-                        answer.status = 0;
-                        answer.content_type = "nickname".to_string();
-                        answer.content = nickname;
-                        return response.send(format!("{}", answer.serialize().unwrap()))
-                    },
-                    None => {
-                        answer.status = 1;
-                        answer.content_type = "err".to_string();
-                        answer.content = "id doesn't exist in database".to_string();
-                        return response.send(format!("{}", answer.serialize().unwrap()))
+            //The champion's chain as a Graphviz graph, optionally pruned with ?top=N.
+            server.get("/champions/:id/dot", {
+                let db = db.clone();
+                let cache = cache.clone();
+                let registry = registry.clone();
+                middleware! { |request, response|
+                    let champion_id = match parse_champion_id(request.param("id")) {
+                        Ok(id) => id,
+                        Err(err) => return response.send(format!("{}", Answer::error(err).serialize().unwrap())),
+                    };
+                    let champion_info = match find_champion(&registry, champion_id) {
+                        Ok(champion) => champion,
+                        Err(err) => return response.send(format!("{}", Answer::error(err).serialize().unwrap())),
+                    };
+                    let top = match request.query().get("top").map(|top| top.parse::<usize>()) {
+                        Some(Ok(top)) => Some(top),
+                        Some(Err(err)) => return response.send(format!("{}", Answer::error(err.to_string()).serialize().unwrap())),
+                        None => None,
+                    };
+                    let champion = match load_champion(&cache, &db, champion_id) {
+                        Ok(champion) => champion,
+                        Err(failure) => return response.send(format!("{}", failure.serialize().unwrap())),
+                    };
+                    if champion.is_empty() {
+                        return response.send(format!("{}", Answer::error("no nickname was fed to this champion yet".to_string()).serialize().unwrap()))
                     }
-                };
+
+                    let answer = Answer {
+                        status: 0,
+                        content_type: "dot".to_string(),
+                        content: Value::String(champion.to_dot(top)),
+                        champion: champion_info};
+                    return response.send(format!("{}", answer.serialize().unwrap()))
+                }
+            });
+            //Admin endpoints, to look at and clean up the stored champions.
+            server.get("/admin/champions", {
+                let db = db.clone();
+                middleware! { |_request, response|
+                    let answer = match db.list() {
+                        Ok(champion_ids) => Answer {
+                            status: 0,
+                            content_type: "champion_ids".to_string(),
+                            content: champion_ids.into_iter().fold(ArrayBuilder::new(), |builder, id| builder.push(id)).unwrap(),
+                            champion: None},
+                        Err(err) => storage_failure(err),
+                    };
+                    return response.send(format!("{}", answer.serialize().unwrap()))
+                }
+            });
+
+            server.get("/admin/champions/:id", {
+                let db = db.clone();
+                middleware! { |request, response|
+                    let champion_id = match parse_champion_id(request.param("id")) {
+                        Ok(id) => id,
+                        Err(err) => return response.send(format!("{}", Answer::error(err).serialize().unwrap())),
+                    };
+                    let answer = match db.exists(champion_id) {
+                        Ok(exists) => Answer {
+                            status: 0,
+                            content_type: "exists".to_string(),
+                            content: Value::Bool(exists),
+                            champion: None},
+                        Err(err) => storage_failure(err),
+                    };
+                    return response.send(format!("{}", answer.serialize().unwrap()))
+                }
             });
+
+            //Deletes the champion and its corpus.
+            server.delete("/admin/champions/:id", {
+                let db = db.clone();
+                let cache = cache.clone();
+                let log = log.clone();
+                middleware! { |request, response|
+                    let champion_id = match parse_champion_id(request.param("id")) {
+                        Ok(id) => id,
+                        Err(err) => return response.send(format!("{}", Answer::error(err).serialize().unwrap())),
+                    };
+                    let answer = match db.delete(champion_id) {
+                        Ok(deleted) => {
+                            log.info("Champion deleted", b!("champion_id" => champion_id, "deleted" => deleted));
+                            Answer {
+                                status: 0,
+                                content_type: "deleted".to_string(),
+                                content: Value::Bool(deleted),
+                                champion: None}
+                        },
+                        Err(err) => storage_failure(err),
+                    };
+                    cache.remove(champion_id);
+                    return response.send(format!("{}", answer.serialize().unwrap()))
+                }
+            });
+
+            //Forgets what the champion learnt, keeping its corpus.
+            server.post("/admin/champions/:id/reset", {
+                let db = db.clone();
+                let cache = cache.clone();
+                let log = log.clone();
+                middleware! { |request, response|
+                    let champion_id = match parse_champion_id(request.param("id")) {
+                        Ok(id) => id,
+                        Err(err) => return response.send(format!("{}", Answer::error(err).serialize().unwrap())),
+                    };
+                    let answer = match db.reset(champion_id) {
+                        Ok(reset) => {
+                            log.info("Champion reset", b!("champion_id" => champion_id, "reset" => reset));
+                            Answer {
+                                status: 0,
+                                content_type: "reset".to_string(),
+                                content: Value::Bool(reset),
+                                champion: None}
+                        },
+                        Err(err) => storage_failure(err),
+                    };
+                    cache.remove(champion_id);
+                    return response.send(format!("{}", answer.serialize().unwrap()))
+                }
+            });
+
             server.listen(SERVER_ADDRESS);
         }
     }
@@ -126,9 +387,6 @@ fn main() {
     root.info("Launching Twitch script", b!());
     let tw_log = root.new(o!("job" => "twitch"));
 
-    //Creation of the db mutex, in order to ensure safety.
-    let db = Mutex::new(Database::new(REDIS_URL.to_string()));
-
     loop {
         tw_log.info("Waking up", o!());
         tw_log.info("Watching for new games", o!());
@@ -161,19 +419,31 @@ fn main() {
             if parsed_answer.get("content_type").unwrap() == "\"nicknames\"" {
 
                 //If it's nicknames, deserialize the data and feed the database.
-                let nicknames_data = Database::deserialize_bulk(parsed_answer.get("content").unwrap().to_owned());
-                match db.lock() {
-                    Ok(db) => {
-                        for (champion_id, nicknames) in nicknames_data {
-                                    let mut champion = db.load(champion_id);
-                                    for nickname in nicknames {
-                                        champion.feed(nickname);
-                                    }
-                                    db.store(&champion);
-                                }
+                //A malformed answer is skipped, the loop goes on with the next one.
+                let nicknames_data = match Database::deserialize_bulk(parsed_answer.get("content").unwrap().to_owned()) {
+                    Ok(nicknames_data) => nicknames_data,
+                    Err(err) => {
+                        tw_log.error("Invalid nicknames sent by Twitch", b!("Error" => err.to_string()));
+                        HashMap::new()
                     },
-
-                    Err(err) => tw_log.error("Impossible to lock the database", b!("Error" => format!("{:?}", &err))),
+                };
+                //Twitch doesn't tell which game the nicknames come from.
+                let bulk : HashMap<u32, Vec<Entry>> = nicknames_data.into_iter().map(|(champion_id, nicknames)| {
+                    let entries = nicknames.into_iter().map(|nickname| {
+                        let mut entry = Entry::new(nickname);
+                        entry.realm = Some(REALM.to_string());
+                        entry
+                    }).collect();
+                    (champion_id, entries)
+                }).collect();
+                for (champion_id, entries) in bulk {
+                    //Champions are fed in place, other coordinators may be feeding them too.
+                    match db.feed(champion_id, &entries) {
+                        Ok(_) => (),
+                        Err(err) => tw_log.error("Impossible to feed the champion", b!("champion_id" => champion_id, "Error" => err.to_string())),
+                    }
+                    //Reloaded on the next request.
+                    cache.remove(champion_id);
                 }
             } else {
                 tw_log.error("content type not yet supported", b!("content_type" => format!("{}", parsed_answer.get("content_type").unwrap())));
@@ -188,6 +458,255 @@ fn main() {
     }
 }
 
+//The storage backend is chosen by the DATABASE_URL environment variable, redis by default.
+fn database_url() -> String {
+    env::var("DATABASE_URL").unwrap_or(REDIS_URL.to_string())
+}
+
+//Connects to the database, waiting for it if it isn't up yet. Only an invalid URL is fatal.
+fn connect_database(log : &Logger) -> Database {
+    let url = database_url();
+    let mut delay = 1;
+    loop {
+        match Database::connect(url.clone()) {
+            Ok(db) => return db,
+            Err(err) => if err.is_temporary() {
+                log.error("Impossible to connect to the database, retrying.", b!("URL" => url.clone(), "Error" => err.to_string(), "delay" => delay));
+            } else {
+                log.critical("Invalid database URL.", b!("URL" => url.clone(), "Error" => err.to_string()));
+                panic!("{}", err);
+            },
+        }
+        thread::sleep(Duration::from_secs(delay));
+        delay = cmp::min(delay * 2, CONNECT_DELAY_MAX);
+    }
+}
+
+//Runs a snapshot subcommand, returning the exit code of the process.
+fn run_command(args : &[String], log : &Logger) -> i32 {
+    let (command, path) = match (args.get(0).map(|arg| arg.as_str()), args.get(1)) {
+        (Some(command @ "export"), Some(path)) | (Some(command @ "import-bulk"), Some(path)) if args.len() == 2 => {
+            (command, path.as_str())
+        },
+        (Some(command @ "import"), Some(path)) if args.len() == 2 || args.len() == 3 && args[2] == "--replace" => {
+            (command, path.as_str())
+        },
+        (Some(command @ "migrate"), None) => (command, "database"),
+        _ => {
+            let _ = writeln!(io::stderr(), "Usage : coordinator [export <file> | import <file> [--replace] | \
+                                            import-bulk <file> | migrate]");
+            return 2;
+        },
+    };
+
+    let db = connect_database(log);
+    let res = match command {
+        //Data written by older versions, like Redis keys without namespace, is moved into the current layout.
+        "migrate" => db.migrate()
+            .map(|moved| format!("Moved {} keys of older versions", moved))
+            .map_err(|err| err.to_string()),
+        "export" => File::create(path).map_err(From::from)
+            .and_then(|file| snapshot::export(&db, BufWriter::new(file)))
+            .map(|stats| snapshot_summary("Exported", &stats))
+            .map_err(|err| err.to_string()),
+        "import" => {
+            let mode = if args.len() == 3 { ImportMode::Replace } else { ImportMode::Merge };
+            File::open(path).map_err(From::from)
+                .and_then(|file| snapshot::import(&db, BufReader::new(file), mode))
+                .map(|stats| snapshot_summary("Imported", &stats))
+                .map_err(|err| err.to_string())
+        },
+        _ => File::open(path).map_err(From::from)
+            .and_then(|file| bulk::import_bulk(&db, file, &BulkOptions::default()))
+            .map(|report| {
+                //One line per champion, so that duplicated ids in the file can be checked.
+                for (champion_id, count) in &report.nicknames {
+                    match report.duplicates.get(champion_id) {
+                        Some(duplicates) => println!("{} : {} nicknames, id repeated {} times", champion_id, count,
+                                                     duplicates + 1),
+                        None => println!("{} : {} nicknames", champion_id, count),
+                    }
+                }
+                format!("Fed {} nicknames to {} champions, in {} batches", report.total(), report.nicknames.len(),
+                        report.batches)
+            })
+            .map_err(|err| err.to_string()),
+    };
+    match res {
+        Ok(summary) => {
+            println!("{} ({}).", summary, path);
+            0
+        },
+        Err(err) => {
+            let _ = writeln!(io::stderr(), "Impossible to {} {} : {}", command, path, err);
+            1
+        },
+    }
+}
+
+fn snapshot_summary(done : &str, stats : &SnapshotStats) -> String {
+    format!("{} {} champions, {} nicknames{}", done, stats.champions, stats.nicknames,
+            if stats.registry { " and the registry" } else { "" })
+}
+
+//Decodes the %XX sequences of a path parameter, like "Kha%27Zix". Invalid sequences are kept as they are.
+fn percent_decode(param : &str) -> String {
+    let bytes = param.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            ::std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+//Checks that the id parameter exists and is a u32.
+fn parse_champion_id(param : Option<&str>) -> Result<u32, String> {
+    match param {
+        Some(id) => id.parse::<u32>().map_err(|err| err.to_string()),
+        None => Err("No id parameter specified.".to_string()),
+    }
+}
+
+//Loads the registry from the database, or from the static data file the first time.
+fn load_registry(db : &Database, log : &Logger) -> Option<ChampionRegistry> {
+    if let Some(registry) = db.load_registry() {
+        log.info("Champion registry loaded.", b!("version" => registry.get_version()));
+        return Some(registry);
+    }
+
+    let mut static_data = String::new();
+    if let Err(err) = File::open(STATIC_DATA_PATH).and_then(|mut file| file.read_to_string(&mut static_data)) {
+        log.warn("Impossible to read static data.", b!("path" => STATIC_DATA_PATH, "err" => err.to_string()));
+        return None;
+    }
+    match ChampionRegistry::from_static_data(&static_data) {
+        Ok(registry) => {
+            if let Err(err) = db.store_registry(&registry) {
+                log.warn("Impossible to store the champion registry.", b!("err" => err.to_string()));
+            }
+            log.info("Champion registry loaded from static data.", b!("version" => registry.get_version()));
+            Some(registry)
+        },
+        Err(err) => {
+            log.warn("Invalid static data.", b!("path" => STATIC_DATA_PATH, "err" => err.to_string()));
+            None
+        }
+    }
+}
+
+//Loads a champion from the cache. The database is only queried when a champion isn't cached or expired.
+//Failures are answered with a distinct status, so that clients can tell a missing champion from a database outage.
+fn load_champion(cache : &ChampionCache, db : &Database, champion_id : u32) -> Result<Arc<Champion>, Answer> {
+    if let Some(champion) = cache.get(champion_id) {
+        return Ok(champion);
+    }
+    match db.load(champion_id) {
+        Ok(Some(champion)) => Ok(cache.insert(champion)),
+        Ok(None) => Err(Answer::failure(STATUS_NOT_FOUND, "id doesn't exist in database".to_string())),
+        Err(err) => Err(storage_failure(err)),
+    }
+}
+
+//Answers a failure of the database.
+fn storage_failure(err : StoreError) -> Answer {
+    match err {
+        StoreError::Corrupt(err) => Answer::failure(STATUS_CORRUPT, format!("champion data is corrupt : {}", err)),
+        err @ StoreError::Conflict(_) => Answer::failure(STATUS_CONFLICT, format!("{}, try again", err)),
+        err => Answer::failure(STATUS_UNAVAILABLE, format!("database unavailable : {}", err)),
+    }
+}
+
+//Generates a nickname for an existing champion, filling the given answer.
+fn generate(champion : &Champion, mut answer : Answer) -> Answer {
+    //Trying to generate a nickname
+    match champion.gen(GEN_LENGTH) {
+        Ok(nickname) => {
+            answer.status = 0;
+            answer.content_type = "nickname".to_string();
+            answer.content = Value::String(nickname);
+        },
+        Err(_) => {
+            answer.content = Value::String("no nickname was fed to this champion yet".to_string());
+        }
+    };
+    answer
+}
+
+//Error message for a name matching no champion, with the closest ones.
+fn unknown_name(name : &str, suggestions : &Vec<&ChampionInfo>) -> String {
+    if suggestions.is_empty() {
+        return format!("no champion is named {:?}", name);
+    }
+    let names : Vec<&str> = suggestions.iter().map(|champion| champion.name.as_str()).collect();
+    format!("no champion is named {:?}, did you mean {} ?", name, names.join(", "))
+}
+
+//Checks that the champion exists, and returns what we know about it.
+fn find_champion(registry : &Option<ChampionRegistry>, champion_id : u32) -> Result<Option<Value>, String> {
+    match *registry {
+        Some(ref registry) => match registry.get(champion_id) {
+            Some(champion) => Ok(Some(champion_to_json(champion))),
+            None => Err(format!("no champion has the id {}", champion_id)),
+        },
+        None => Ok(None),
+    }
+}
+
+fn champion_to_json(champion : &ChampionInfo) -> Value {
+    ObjectBuilder::new()
+        .insert("id", champion.id)
+        .insert("key", champion.key.clone())
+        .insert("name", champion.name.clone())
+        .insert("title", champion.title.clone())
+        .unwrap()
+}
+
+//Parses an optional query parameter, using the default value if it's missing.
+fn parse_query_param(param : Option<&str>, default : u32) -> Result<u32, String> {
+    match param {
+        Some(value) => value.parse::<u32>().map_err(|err| err.to_string()),
+        None => Ok(default),
+    }
+}
+
+//Only the most common starting and ending letters are worth displaying.
+const STATS_TOP_LETTERS : usize = 10;
+
+fn stats_to_json(stats : &Stats) -> Value {
+    let letters_to_json = |letters : &Vec<(char, u64)>| {
+        letters.iter().take(STATS_TOP_LETTERS).fold(ArrayBuilder::new(), |builder, &(letter, count)| {
+            builder.push_object(|obj| obj.insert("letter", letter).insert("count", count))
+        }).unwrap()
+    };
+    ObjectBuilder::new()
+        .insert("nicknames", stats.nicknames)
+        .insert("states", stats.states)
+        .insert("transitions", stats.transitions)
+        .insert("total_count", stats.total_count)
+        .insert("average_length", stats.average_length)
+        .insert("entropy", stats.entropy.iter().fold(ObjectBuilder::new(), |builder, (state, entropy)| {
+            builder.insert(state.clone(), *entropy)
+        }).unwrap())
+        .insert("starts", letters_to_json(&stats.starts))
+        .insert("ends", letters_to_json(&stats.ends))
+        .unwrap()
+}
+
 //TODO: Error handling
 fn deserialize_answer(answer : String) -> HashMap<String, String> {
 
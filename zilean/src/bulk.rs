@@ -0,0 +1,381 @@
+//! Imports bulk nickname files, `{champion_id: [nicknames]}` objects like `zilean/db.json` or what Twitch sends.
+//!
+//! These files are written by concatenating the nicknames of several games, so a champion id may appear more than
+//! once : `{"150": ["Odoamne"], "4": ["Kxeight"], "150": ["VG LeLeNaGa"]}`. A JSON map would keep a single one of
+//! them, so they are read by a streaming parser handing out every pair, and the nicknames of repeated ids are merged.
+//!
+//! # Examples
+//!
+//! ```
+//! use self::zilean::bulk::*;
+//! use self::zilean::database::*;
+//!
+//! let db = Database::new("memory://".to_string());
+//! let file = r#"{"150": ["Odoamne"], "4": ["Kxeight"], "150": ["VG LeLeNaGa"]}"#;
+//! let report = import_bulk(&db, file.as_bytes(), &BulkOptions::default()).unwrap();
+//! assert_eq!(Some(&2), report.nicknames.get(&150));
+//! assert_eq!(Some(&1), report.duplicates.get(&150));
+//! assert_eq!(2, db.load_corpus(150).len());
+//! ```
+use ::corpus::Entry as Entry;
+use ::database::Database as Database;
+use ::store::StoreError as StoreError;
+use std::char;
+use std::collections::{BTreeMap, HashMap};
+use std::error;
+use std::fmt;
+use std::io::{self, BufReader, Read};
+use std::mem;
+
+/// Errors that can happen while importing a bulk file.
+#[derive(Debug)]
+pub enum BulkError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// The file isn't a `{champion_id: [nicknames]}` object. Holds the byte offset of the error, and what was wrong.
+    Syntax(usize, String),
+    /// The database failed to answer.
+    Store(StoreError),
+}
+
+impl fmt::Display for BulkError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BulkError::Io(ref err) => write!(f, "I/O error : {}", err),
+            BulkError::Syntax(offset, ref err) => write!(f, "Invalid bulk file at byte {} : {}", offset, err),
+            BulkError::Store(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for BulkError {
+    fn description(&self) -> &str {
+        match *self {
+            BulkError::Io(_) => "i/o error",
+            BulkError::Syntax(..) => "invalid bulk file",
+            BulkError::Store(_) => "store error",
+        }
+    }
+}
+
+impl From<io::Error> for BulkError {
+    fn from(err : io::Error) -> BulkError {
+        BulkError::Io(err)
+    }
+}
+
+impl From<StoreError> for BulkError {
+    fn from(err : StoreError) -> BulkError {
+        BulkError::Store(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Start,
+    First,
+    Next,
+    Done,
+}
+
+/// Reads the `(champion_id, nicknames)` pairs of a bulk file one at a time, in the order of the file.
+///
+/// Repeated champion ids are handed out as many times as they appear. After an error, no more pairs are read.
+///
+/// # Example
+///
+/// ```
+/// use self::zilean::bulk::*;
+///
+/// let file = r#"{"150": ["Odoamne"], "4": [], "150": ["VG LeLeNaGa", "Éndz"]}"#;
+/// let pairs : Vec<_> = BulkReader::new(file.as_bytes()).map(|pair| pair.unwrap()).collect();
+/// assert_eq!(vec![(150, vec!["Odoamne".to_string()]),
+///                 (4, vec![]),
+///                 (150, vec!["VG LeLeNaGa".to_string(), "Éndz".to_string()])], pairs);
+/// ```
+pub struct BulkReader<R> {
+    reader : BufReader<R>,
+    peeked : Option<u8>,
+    offset : usize,
+    state : State,
+}
+
+impl<R> BulkReader<R> where R : Read {
+    /// Creates a reader, buffering `reader`.
+    pub fn new(reader : R) -> BulkReader<R> {
+        BulkReader {
+            reader : BufReader::new(reader),
+            peeked : None,
+            offset : 0,
+            state : State::Start,
+        }
+    }
+
+    fn next_pair(&mut self) -> Result<Option<(u32, Vec<String>)>, BulkError> {
+        match self.state {
+            State::Done => return Ok(None),
+            State::Start => {
+                try!(self.expect(b'{'));
+                self.state = State::First;
+            },
+            _ => (),
+        }
+        match try!(self.peek_token()) {
+            Some(b'}') if self.state == State::First => return self.end(),
+            _ if self.state == State::First => (),
+            Some(b'}') => return self.end(),
+            _ => try!(self.expect(b',')),
+        }
+        self.state = State::Next;
+
+        let key_offset = self.offset;
+        let key = try!(self.read_string());
+        let champion_id = try!(key.parse::<u32>()
+            .map_err(|_| BulkError::Syntax(key_offset, format!("{:?} isn't a champion id", key))));
+        try!(self.expect(b':'));
+        try!(self.expect(b'['));
+        let mut nicknames = Vec::new();
+        if try!(self.peek_token()) == Some(b']') {
+            self.bump();
+        } else {
+            loop {
+                nicknames.push(try!(self.read_string()));
+                match try!(self.next_token()) {
+                    Some(b',') => (),
+                    Some(b']') => break,
+                    _ => return Err(self.syntax("expected ',' or ']'")),
+                }
+            }
+        }
+        Ok(Some((champion_id, nicknames)))
+    }
+
+    fn end(&mut self) -> Result<Option<(u32, Vec<String>)>, BulkError> {
+        self.bump();
+        self.state = State::Done;
+        match try!(self.peek_token()) {
+            None => Ok(None),
+            Some(_) => Err(self.syntax("trailing characters")),
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, BulkError> {
+        try!(self.expect(b'"'));
+        let start = self.offset;
+        let mut bytes = Vec::new();
+        loop {
+            match try!(self.next_byte()) {
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    let escaped = match try!(self.next_byte()) {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\x08',
+                        Some(b'f') => '\x0c',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => try!(self.read_unicode_escape()),
+                        _ => return Err(self.syntax("invalid escape")),
+                    };
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut buf).as_bytes());
+                },
+                Some(byte) if byte < 0x20 => return Err(self.syntax("control character in a string")),
+                Some(byte) => bytes.push(byte),
+                None => return Err(self.syntax("unterminated string")),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| BulkError::Syntax(start, "invalid UTF-8".to_string()))
+    }
+
+    fn read_unicode_escape(&mut self) -> Result<char, BulkError> {
+        let high = try!(self.read_hex());
+        let code = match high {
+            0xD800 ... 0xDBFF => {
+                //Characters outside the basic plane are escaped as a surrogate pair.
+                if try!(self.next_byte()) != Some(b'\\') || try!(self.next_byte()) != Some(b'u') {
+                    return Err(self.syntax("unpaired surrogate"));
+                }
+                let low = try!(self.read_hex());
+                if low < 0xDC00 || low > 0xDFFF {
+                    return Err(self.syntax("unpaired surrogate"));
+                }
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            },
+            code => code,
+        };
+        char::from_u32(code).ok_or(self.syntax("invalid unicode escape"))
+    }
+
+    fn read_hex(&mut self) -> Result<u32, BulkError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = try!(self.next_byte()).and_then(|byte| (byte as char).to_digit(16));
+            match digit {
+                Some(digit) => code = code * 16 + digit,
+                None => return Err(self.syntax("invalid unicode escape")),
+            }
+        }
+        Ok(code)
+    }
+
+    fn expect(&mut self, expected : u8) -> Result<(), BulkError> {
+        match try!(self.next_token()) {
+            Some(byte) if byte == expected => Ok(()),
+            _ => Err(self.syntax(format!("expected '{}'", expected as char))),
+        }
+    }
+
+    //Skips whitespace, then returns the next byte without consuming it.
+    fn peek_token(&mut self) -> Result<Option<u8>, BulkError> {
+        loop {
+            match try!(self.peek_byte()) {
+                Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') => self.bump(),
+                byte => return Ok(byte),
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<u8>, BulkError> {
+        let byte = try!(self.peek_token());
+        self.bump();
+        Ok(byte)
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>, BulkError> {
+        if self.peeked.is_none() {
+            let mut buf = [0; 1];
+            loop {
+                match self.reader.read(&mut buf) {
+                    Ok(0) => return Ok(None),
+                    Ok(_) => break,
+                    Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+                    Err(err) => return Err(BulkError::Io(err)),
+                }
+            }
+            self.peeked = Some(buf[0]);
+        }
+        Ok(self.peeked)
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>, BulkError> {
+        let byte = try!(self.peek_byte());
+        self.bump();
+        Ok(byte)
+    }
+
+    fn bump(&mut self) {
+        if self.peeked.take().is_some() {
+            self.offset += 1;
+        }
+    }
+
+    fn syntax<S>(&self, err : S) -> BulkError where S : Into<String> {
+        BulkError::Syntax(self.offset, err.into())
+    }
+}
+
+impl<R> Iterator for BulkReader<R> where R : Read {
+    type Item = Result<(u32, Vec<String>), BulkError>;
+
+    fn next(&mut self) -> Option<Result<(u32, Vec<String>), BulkError>> {
+        match self.next_pair() {
+            Ok(pair) => pair.map(Ok),
+            Err(err) => {
+                self.state = State::Done;
+                Some(Err(err))
+            },
+        }
+    }
+}
+
+/// How `import_bulk` feeds the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkOptions {
+    /// The number of nicknames read before the champions they belong to are fed, see `Database::train_bulk`.
+    pub batch_size : usize,
+    /// The number of threads feeding the champions of a batch.
+    pub workers : usize,
+    /// The realm of the games the nicknames come from, if known.
+    pub realm : Option<String>,
+}
+
+impl Default for BulkOptions {
+    fn default() -> BulkOptions {
+        BulkOptions {
+            batch_size : 10000,
+            workers : 4,
+            realm : None,
+        }
+    }
+}
+
+/// What `import_bulk` read.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BulkReport {
+    /// The number of nicknames read, by champion id.
+    pub nicknames : BTreeMap<u32, usize>,
+    /// How many more times than once a champion id appeared, for the ids appearing more than once.
+    pub duplicates : BTreeMap<u32, usize>,
+    /// The number of batches fed to the database.
+    pub batches : usize,
+}
+
+impl BulkReport {
+    /// Returns the number of nicknames read, over every champion.
+    pub fn total(&self) -> usize {
+        self.nicknames.values().sum()
+    }
+}
+
+/// Reads a bulk file from `reader`, and feeds its nicknames to the champions of the database, and to their corpus.
+///
+/// Nicknames are fed by batches of about `options.batch_size`, so that the file is never loaded in memory at once.
+/// The nicknames of a champion id appearing more than once are merged, even across batches.
+///
+/// # Arguments
+///
+/// * `db` : the database to feed.
+/// * `reader` : where the bulk file is read from, like a file.
+/// * `options` : how the database is fed.
+///
+/// # Errors
+///
+/// If the file can't be read or is invalid, or if the database fails to answer. The batches fed before the error stay
+/// in the database.
+pub fn import_bulk<R>(db : &Database, reader : R, options : &BulkOptions) -> Result<BulkReport, BulkError> where R : Read {
+    let mut report = BulkReport::default();
+    let mut batch : HashMap<u32, Vec<Entry>> = HashMap::new();
+    let mut pending = 0;
+    let mut seen = HashMap::new();
+    for pair in BulkReader::new(reader) {
+        let (champion_id, nicknames) = try!(pair);
+        let occurrences = seen.entry(champion_id).or_insert(0);
+        *occurrences += 1;
+        if *occurrences > 1 {
+            report.duplicates.insert(champion_id, *occurrences - 1);
+        }
+        *report.nicknames.entry(champion_id).or_insert(0) += nicknames.len();
+        if nicknames.is_empty() {
+            continue;
+        }
+        pending += nicknames.len();
+        batch.entry(champion_id).or_insert_with(Vec::new).extend(nicknames.into_iter().map(|nickname| {
+            let mut entry = Entry::new(nickname);
+            entry.realm = options.realm.clone();
+            entry
+        }));
+        if pending >= options.batch_size {
+            try!(db.train_bulk(mem::replace(&mut batch, HashMap::new()), options.workers));
+            report.batches += 1;
+            pending = 0;
+        }
+    }
+    if !batch.is_empty() {
+        try!(db.train_bulk(batch, options.workers));
+        report.batches += 1;
+    }
+    Ok(report)
+}
@@ -0,0 +1,122 @@
+//! Shares read-only champions between threads.
+//!
+//! Champions are kept behind an `Arc`, so that many threads can generate from the same champion at once. The cache is
+//! only locked for writing when a champion is inserted or removed.
+//!
+//! A process only sees its own writes. When the store is shared, with other coordinators or with `import` runs, the
+//! cache should be created with `with_ttl`, so that champions are loaded again after a while.
+//!
+//! # Examples
+//!
+//! ```
+//! use self::zilean::cache::*;
+//! use self::zilean::champion::*;
+//! use std::sync::Arc;
+//! use std::thread;
+//!
+//! let cache = Arc::new(ChampionCache::new());
+//! let mut champion = Champion::new(10);
+//! champion.feed("foo".to_string());
+//! cache.insert(champion);
+//!
+//! let workers : Vec<_> = (0..4).map(|_| {
+//!     let cache = cache.clone();
+//!     thread::spawn(move || cache.get(10).unwrap().gen(16).unwrap())
+//! }).collect();
+//! for worker in workers {
+//!     println!("{}", worker.join().unwrap());
+//! }
+//! ```
+use ::champion::Champion as Champion;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Champions shared between threads, by id.
+pub struct ChampionCache {
+    //With the time each champion was cached.
+    champions : RwLock<HashMap<u32, (Arc<Champion>, Instant)>>,
+    ttl : Option<Duration>,
+}
+
+impl ChampionCache {
+    /// Creates an empty cache, keeping champions until they are removed.
+    pub fn new() -> ChampionCache {
+        ChampionCache {
+            champions : RwLock::new(HashMap::new()),
+            ttl : None,
+        }
+    }
+
+    /// Creates an empty cache, keeping champions for `ttl` at most, so that what other processes store is seen.
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl` : how long a champion is served from the cache before being loaded again.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::cache::*;
+    /// use self::zilean::champion::*;
+    /// use std::time::Duration;
+    ///
+    /// let cache = ChampionCache::with_ttl(Duration::from_secs(0));
+    /// cache.insert(Champion::new(10));
+    /// assert!(cache.get(10).is_none());
+    /// ```
+    pub fn with_ttl(ttl : Duration) -> ChampionCache {
+        ChampionCache {
+            champions : RwLock::new(HashMap::new()),
+            ttl : Some(ttl),
+        }
+    }
+
+    /// Returns the cached champion, if any and if it didn't expire.
+    pub fn get(&self, champion_id : u32) -> Option<Arc<Champion>> {
+        //A thread panicking while holding the lock can't leave the map half updated, so we keep using it.
+        let champions = match self.champions.read() {
+            Ok(champions) => champions,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        //Expired champions are left in place, the next insert replaces them.
+        match champions.get(&champion_id) {
+            Some(&(ref champion, cached)) if self.ttl.map_or(true, |ttl| cached.elapsed() < ttl) => {
+                Some(champion.clone())
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the cached champion, or caches the one `load` returns.
+    ///
+    /// `load` is called without holding the lock, so two threads missing the same champion may both call it.
+    pub fn get_or_load<F>(&self, champion_id : u32, load : F) -> Arc<Champion> where F : FnOnce(u32) -> Champion {
+        match self.get(champion_id) {
+            Some(champion) => champion,
+            None => self.insert(load(champion_id)),
+        }
+    }
+
+    /// Caches a champion, replacing the previous one with the same id.
+    ///
+    /// Threads already using the previous one keep it until they are done.
+    pub fn insert(&self, champion : Champion) -> Arc<Champion> {
+        let champion = Arc::new(champion);
+        let mut champions = match self.champions.write() {
+            Ok(champions) => champions,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        champions.insert(champion.get_id(), (champion.clone(), Instant::now()));
+        champion
+    }
+
+    /// Removes a champion from the cache, so that it is loaded again next time.
+    pub fn remove(&self, champion_id : u32) -> Option<Arc<Champion>> {
+        let mut champions = match self.champions.write() {
+            Ok(champions) => champions,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        champions.remove(&champion_id).map(|(champion, _)| champion)
+    }
+}
@@ -31,20 +31,174 @@
 //! let mut deserialized = Champion::new(10);
 //! deserialized.deserialize(serialized);
 //! ```
+//!
+//! ## Chain order
+//!
+//! By default, the next letter only depends on the previous one. A higher order makes it depend on more letters,
+//! giving nicknames closer to the ones fed.
+//!
+//! ```
+//! use self::zilean::champion::*;
+//! let mut champion = Champion::with_order(10, 2);
+//!
+//! champion.feed("foo".to_string());
+//! champion.feed("bar".to_string());
+//!
+//! println!("Generated nickname : {}", champion.gen(10).unwrap());
+//! ```
 extern crate slog;
 extern crate rand;
 extern crate serde_json;
-use std::collections::HashMap;
-use std::collections::hash_map::Entry::{Occupied, Vacant};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::error;
+use std::f64;
+use std::fmt;
+use std::iter;
 use self::rand::Rng;
+use self::serde_json::Value;
+use self::serde_json::builder::ObjectBuilder;
+
+/// Ends every nickname. The state made of it alone holds the letters nicknames start with.
+pub const TERMINATOR : char = '\0';
+
+//Bound the work and the memory of Champion::top on chains with many likely nicknames.
+const MAX_TOP_STEPS : usize = 20000;
+const MAX_TOP_CANDIDATES : usize = 10000;
+//How many generated nicknames in a row the filters of Nicknames can reject before the iterator stops, considering
+//that the chain can't give any more.
+const MAX_REJECTED : usize = 1000;
+
+/// Errors that can happen while generating a nickname.
+#[derive(Debug, PartialEq, Eq)]
+pub enum GenError {
+    /// The champion has no data to generate from.
+    EmptyModel,
+}
+
+impl fmt::Display for GenError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", error::Error::description(self))
+    }
+}
+
+impl error::Error for GenError {
+    fn description(&self) -> &str {
+        match *self {
+            GenError::EmptyModel => "no data to generate a nickname from",
+        }
+    }
+}
+
+/// Errors that can happen while loading a champion from JSON, see `Champion::try_deserialize`.
+#[derive(Debug)]
+pub enum DeserializeError {
+    /// The string isn't JSON, or doesn't hold a chain.
+    Json(serde_json::Error),
+    /// The chain order is 0.
+    InvalidOrder,
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DeserializeError::Json(ref err) => write!(f, "invalid chain : {}", err),
+            DeserializeError::InvalidOrder => write!(f, "a chain order must be at least 1"),
+        }
+    }
+}
 
+impl error::Error for DeserializeError {
+    fn description(&self) -> &str {
+        match *self {
+            DeserializeError::Json(_) => "invalid chain",
+            DeserializeError::InvalidOrder => "invalid chain order",
+        }
+    }
+}
 
+impl From<serde_json::Error> for DeserializeError {
+    fn from(err : serde_json::Error) -> DeserializeError {
+        DeserializeError::Json(err)
+    }
+}
+
+/// Result of `Champion::validate`.
+///
+/// States are the last letters generated, as many as the chain order. A `\0` stands for the beginning of the nickname.
 #[derive(Debug, PartialEq, Eq)]
+pub struct Validation {
+    /// States that can be generated but have no successor. Generation stops early on them.
+    pub dead_ends : Vec<String>,
+    /// States that have successors but can't be reached from the beginning of a nickname.
+    pub unreachable : Vec<String>,
+}
+
+impl Validation {
+    /// Returns `true` if no dead end nor unreachable letter was found.
+    pub fn is_valid(&self) -> bool {
+        self.dead_ends.is_empty() && self.unreachable.is_empty()
+    }
+}
+
+/// Result of `Champion::stats`.
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    /// Number of nicknames that fed the chain.
+    pub nicknames : u64,
+    /// Number of states having successors.
+    pub states : usize,
+    /// Number of distinct links between a state and a letter, the terminating character included.
+    pub transitions : usize,
+    /// Sum of the occurrences of every link, starting links included.
+    pub total_count : u64,
+    /// Average length of the nicknames that fed the chain.
+    pub average_length : f64,
+    /// Shannon entropy of the successors of each state, in bits.
+    pub entropy : HashMap<String, f64>,
+    /// Letters nicknames start with, most common first.
+    pub starts : Vec<(char, u64)>,
+    /// Letters nicknames end with, most common first.
+    pub ends : Vec<(char, u64)>,
+}
+
+//A nickname being built by Champion::top. The heap pops the most probable one first.
+struct Candidate {
+    log_probability : f64,
+    state : String,
+    nickname : String,
+    complete : bool,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other : &Candidate) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other : &Candidate) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    //Probabilities are never NaN, ties are broken by nickname so that the order is stable.
+    fn cmp(&self, other : &Candidate) -> Ordering {
+        self.log_probability.partial_cmp(&other.log_probability).unwrap_or(Ordering::Equal)
+            .then_with(|| other.nickname.cmp(&self.nickname))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 ///Represents all the nicknames associated with a certain champion, characterized by its id.
 //SHOULD IT BE A SINGLETON ?
 pub struct Champion {
     id: u32,
-    values: HashMap<char, HashMap<char, u64>>,
+    order: usize,
+    values: HashMap<String, HashMap<char, u64>>,
 }
 
 impl Champion {
@@ -57,17 +211,39 @@ impl Champion {
     /// let champion = Champion::new(10);
     /// ```
     pub fn new(id : u32) -> Champion {
+        Champion::with_order(id, 1)
+    }
+
+    /// Constructs a new `Champion` by its id, where each letter depends on the `order` previous ones.
+    ///
+    /// # Example
+    /// ```
+    /// use self::zilean::champion::*;
+    ///
+    /// let champion = Champion::with_order(10, 3);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `order` is 0.
+    pub fn with_order(id : u32, order : usize) -> Champion {
+        assert!(order > 0, "A chain order must be at least 1");
         Champion {
             id : id,
+            order : order,
             values : HashMap::new(),
         }
     }
 
-    /// Feeds a `String` onto the generator.
+    /// Feeds a nickname onto the generator.
+    ///
+    /// Counters are incremented in place : the only allocations are the keys of states seen for the first time.
+    ///
+    /// Returns `true` if the chain learnt something, `false` if the nickname was empty or could already be generated.
     ///
     /// # Arguments
     ///
-    /// * `nickname` : The nickname which will feed the chain
+    /// * `nickname` : The nickname which will feed the chain, a `String` or a `&str`.
     ///
     /// # Example
     ///
@@ -75,39 +251,62 @@ impl Champion {
     /// use self::zilean::champion::*;
     ///
     ///  let mut champion = Champion::new(10);
-    ///  champion.feed("foobar".to_string());
-    /// ```
-    pub fn feed(&mut self, nickname : String) {
-        //We see if the nickname was already parsed
-        if !Champion::contains(&self, nickname.clone()) {
-            //We split nickname in pairs of two chars
-            let splitted = Champion::split_nickname(nickname).unwrap();
-            /*
-                For each pair (0, 1), we see if 1 is already a key.
-                If it's the case, either we increment by one the counter of this char if we already have a link between 0 and 1,
-                or we simply create another entry.
-            */
-            for pair in &splitted {
-                let mut letter_hm : HashMap<char, u64>;
-                if self.values.contains_key(&pair[0]) {
-                    letter_hm = self.values.get(&pair[0]).unwrap().clone();
-                    let pair_hm = match letter_hm.entry(pair[1]) {
-                        Vacant(entry) => entry.insert(0),
-                        Occupied(entry) => entry.into_mut(),
-                    };
-                    *pair_hm += 1;
-                } else {
-                    letter_hm = HashMap::new();
-                    letter_hm.insert(pair[1], 1);
-                }
-                self.values.insert(pair[0], letter_hm.clone());
+    ///  assert!(champion.feed("foobar".to_string()));
+    ///  assert!(!champion.feed("foobar"));
+    /// ```
+    pub fn feed<S>(&mut self, nickname : S) -> bool where S : AsRef<str> {
+        let nickname = nickname.as_ref();
+        //We see if the nickname was already parsed. Empty nicknames carry no data.
+        if nickname.is_empty() || self.contains(nickname) {
+            return false;
+        }
+        //The state is updated in place instead of splitting the nickname in (state, next letter) pairs.
+        let mut state = String::with_capacity(self.order * 4);
+        state.push(TERMINATOR);
+        for letter in nickname.chars().chain(iter::once(TERMINATOR)) {
+            let known = match self.values.get_mut(state.as_str()) {
+                Some(successors) => {
+                    *successors.entry(letter).or_insert(0) += 1;
+                    true
+                },
+                None => false,
+            };
+            if !known {
+                let mut successors = HashMap::new();
+                successors.insert(letter, 1);
+                self.values.insert(state.clone(), successors);
             }
+            Champion::push_letter(&mut state, letter, self.order);
         }
+        true
+    }
+
+    /// Feeds every nickname of an iterator, see `feed`.
+    ///
+    /// Returns the number of nicknames the chain learnt something from.
+    ///
+    /// # Arguments
+    ///
+    /// * `nicknames` : The nicknames, as `&str` or `String`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    ///
+    /// let mut champion = Champion::new(10);
+    /// assert_eq!(2, champion.feed_all(vec!["foo", "bar", "foo", ""]));
+    /// ```
+    pub fn feed_all<I>(&mut self, nicknames : I) -> usize where I : IntoIterator, I::Item : AsRef<str> {
+        nicknames.into_iter().filter(|nickname| self.feed(nickname)).count()
     }
 
     //TODO: Use scan() in order to produce something nice and compact.
     /// Generates a nickname.
     ///
+    /// The first letter is drawn from the letters nicknames were seen starting with. Generation stops when we reach
+    /// `max_len`, when we draw the terminating character or when the current state has no known successor.
+    ///
     /// # Arguments
     ///
     /// * `max_len` : The maximum length of the nickname.
@@ -122,63 +321,315 @@ impl Champion {
     /// println!("{}", champion.gen(10).unwrap());
     /// ```
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If it is unable to generate some other character. (That's a bad thing)
-    pub fn gen(&self, max_len : u32) -> Option<String> {
-        if self.values.len() == 0 {
-            return None;
-        }
-        let mut rng = rand::thread_rng();
-        //First, we generate our starting letter.
-        let first_key_index = rng.gen_range(0, self.values.keys().len());
-        let mut next_key = self.values.keys().nth(first_key_index).unwrap().clone();
-        let mut ret = "".to_string();
+    /// `GenError::EmptyModel` if the champion has not been fed yet.
+    pub fn gen(&self, max_len : u32) -> Result<String, GenError> {
+        let (mut state, mut ret) = match self.get_first_state() {
+            Some(first) => first,
+            None => return Err(GenError::EmptyModel),
+        };
         //Now we will generate next letters until we reach max_len or we hit a terminating char.
-        for _ in 0..max_len {
-            //quickfix for \0
-            if next_key != '\0' {
-                ret = ret + &next_key.to_string();
-            }
-            // XXX : Is it dirty ?
-            match next_key {
-                '\0' => break,
-                _ => next_key = match Champion::get_next_letter(&self.values.get(&next_key).unwrap().clone()) {
-                    Some(letter) => letter,
-                    None => panic!("Error during nickname generation"),
-                }
+        while (ret.chars().count() as u32) < max_len {
+            //A state without successors is a dead end (edited or pruned data) : we stop there instead of panicking.
+            let next_key = match self.values.get(&state).and_then(Champion::get_next_letter) {
+                Some(letter) => letter,
+                None => break,
+            };
+            if next_key == TERMINATOR {
+                break;
             }
+            ret.push(next_key);
+            state = self.next_state(&state, next_key);
+        }
+        Ok(ret)
+    }
+
+    //Returns the state generation starts from, with the letters it already generated.
+    //Data stored before starting letters were recorded has no start state : we fall back to any known state.
+    fn get_first_state(&self) -> Option<(String, String)> {
+        let start = TERMINATOR.to_string();
+        if self.values.contains_key(&start) {
+            return Some((start, String::new()));
+        }
+        if self.values.is_empty() {
+            return None;
+        }
+        let index = rand::thread_rng().gen_range(0, self.values.len());
+        self.values.keys().nth(index).map(|state| {
+            (state.clone(), state.chars().filter(|letter| *letter != TERMINATOR).collect())
+        })
+    }
+
+    //The state we are in after generating `letter` from `state` : the `order` last letters.
+    fn next_state(&self, state : &str, letter : char) -> String {
+        let mut next = state.to_string();
+        Champion::push_letter(&mut next, letter, self.order);
+        next
+    }
+
+    //Turns `state` into the next state in place, keeping its `order` last letters.
+    fn push_letter(state : &mut String, letter : char, order : usize) {
+        state.push(letter);
+        while state.chars().count() > order {
+            state.remove(0);
         }
-        return Some(ret)
     }
 
     fn get_next_letter(current_letter : &HashMap<char, u64>) -> Option<char> {
-        //We get the sum of all the occurrences of all the successors of current_letter, and we gen a random number between 0 and sum+1
+        //We get the sum of all the occurrences of all the successors of current_letter, and we gen a random number between 0 and sum
         let sum = Champion::get_sum(current_letter);
-        let rng = rand::thread_rng().gen_range(0, sum+1);
+        if sum == 0 {
+            return None;
+        }
+        let rng = rand::thread_rng().gen_range(0, sum);
         let mut partial_sum = 0;
         /* As we iterate over the potential successors, we accumulate their occurences, and we stop if this accumulation
-           is superior or equal to the random generated number.
+           is superior to the random generated number.
         */
         for letter in current_letter {
             partial_sum = partial_sum + letter.1;
-            if partial_sum >= rng {
+            if partial_sum > rng {
                 return Some(letter.0.clone())
             }
         }
         None
     }
 
+    /// Returns an iterator of generated nicknames, to be bounded with `take`.
+    ///
+    /// Without filters, it never stops unless the champion has no data. With filters, like `unique` or `novel`, it
+    /// stops once they rejected 1000 generated nicknames in a row, which means the chain can't give any more.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_len` : The maximum length of the nicknames.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    ///
+    /// let mut champion = Champion::new(10);
+    /// champion.feed("foo".to_string());
+    /// champion.feed("bar".to_string());
+    ///
+    /// let nicknames : Vec<String> = champion.nicknames(16)
+    ///     .unique()
+    ///     .valid(3)
+    ///     .novel(vec!["foo".to_string(), "bar".to_string()])
+    ///     .take(5)
+    ///     .collect();
+    /// ```
+    pub fn nicknames(&self, max_len : u32) -> Nicknames {
+        Nicknames {
+            champion : self,
+            max_len : max_len,
+            min_len : 0,
+            unique : false,
+            seen : HashSet::new(),
+            known : HashSet::new(),
+        }
+    }
+
+    /// Returns the `k` nicknames the chain is the most likely to generate, with their probability, most likely first.
+    ///
+    /// This is a best-first search : partial nicknames are extended from the most probable one, so complete nicknames
+    /// come out in decreasing probability. Only the most probable partial nicknames are kept, a few thousands, so a
+    /// nickname whose beginning is very unlikely can be missed. The search gives up after a fixed number of steps, in
+    /// which case fewer than `k` nicknames are returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `k` : The number of nicknames wanted.
+    /// * `min_len` : The minimum length of the nicknames.
+    /// * `max_len` : The maximum length of the nicknames.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    ///
+    /// let mut champion = Champion::new(10);
+    /// champion.feed("foo".to_string());
+    /// champion.feed("bar".to_string());
+    /// for (nickname, probability) in champion.top(5, 1, 16) {
+    ///     println!("{} : {}", nickname, probability);
+    /// }
+    /// ```
+    pub fn top(&self, k : usize, min_len : u32, max_len : u32) -> Vec<(String, f64)> {
+        let mut ret = Vec::new();
+        let mut heap = BinaryHeap::new();
+        let start = TERMINATOR.to_string();
+        if self.values.contains_key(&start) {
+            heap.push(Candidate { log_probability : 0.0, state : start, nickname : String::new(), complete : false });
+        } else {
+            //Without a start state, generation picks any state : they're all equally likely.
+            let log_probability = -(self.values.len() as f64).log2();
+            for state in self.values.keys() {
+                let nickname : String = state.chars().filter(|letter| *letter != TERMINATOR).collect();
+                heap.push(Candidate { log_probability : log_probability, state : state.clone(), nickname : nickname, complete : false });
+            }
+        }
+
+        let mut steps = 0;
+        while let Some(candidate) = heap.pop() {
+            if ret.len() >= k || steps >= MAX_TOP_STEPS {
+                break;
+            }
+            steps += 1;
+            if candidate.complete {
+                ret.push((candidate.nickname, candidate.log_probability.exp2()));
+                continue;
+            }
+            let successors = match self.values.get(&candidate.state) {
+                Some(successors) => successors,
+                None => continue,
+            };
+            let sum = Champion::get_sum(successors) as f64;
+            let len = candidate.nickname.chars().count() as u32;
+            for (letter, count) in successors {
+                let log_probability = candidate.log_probability + (*count as f64 / sum).log2();
+                if *letter == TERMINATOR {
+                    if len >= min_len {
+                        heap.push(Candidate { log_probability : log_probability, state : String::new(),
+                                              nickname : candidate.nickname.clone(), complete : true });
+                    }
+                } else if len < max_len {
+                    let mut nickname = candidate.nickname.clone();
+                    nickname.push(*letter);
+                    heap.push(Candidate { log_probability : log_probability, state : self.next_state(&candidate.state, *letter),
+                                          nickname : nickname, complete : false });
+                }
+            }
+            //Each step can push as many candidates as there are letters : the least probable ones are dropped once
+            //there are twice too many, so that memory stays bounded.
+            if heap.len() > 2 * MAX_TOP_CANDIDATES {
+                let mut candidates = heap.into_sorted_vec();
+                let dropped = candidates.len() - MAX_TOP_CANDIDATES;
+                candidates.drain(..dropped);
+                heap = BinaryHeap::from(candidates);
+            }
+        }
+        ret
+    }
+
+    /// Returns the base 2 logarithm of the probability of generating exactly `nickname`, and the number of letters
+    /// drawn to do so, the terminating character included.
+    ///
+    /// With additive smoothing, every letter gets `smoothing` more occurrences after every state, so that letters
+    /// never seen after a state don't make the probability drop to 0 (a logarithm of minus infinity).
+    ///
+    /// # Arguments
+    ///
+    /// * `nickname` : The nickname to score.
+    /// * `smoothing` : The occurrences added to every letter, 0 for none.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    ///
+    /// let mut champion = Champion::new(10);
+    /// champion.feed("ab".to_string());
+    /// assert_eq!((0.0, 3), champion.log_probability("ab", 0.0));
+    /// ```
+    pub fn log_probability(&self, nickname : &str, smoothing : f64) -> (f64, usize) {
+        let splitted = match self.split_nickname(nickname.to_string()) {
+            Some(splitted) => splitted,
+            None => return (0.0, 0),
+        };
+        //Every letter the chain knows, plus one for the ones it doesn't.
+        let mut alphabet : Vec<char> = self.values.values().flat_map(|successors| successors.keys().cloned()).collect();
+        alphabet.sort();
+        alphabet.dedup();
+        let alphabet_size = (alphabet.len() + 1) as f64;
+
+        let mut log_probability = 0f64;
+        for &(ref state, letter) in &splitted {
+            let (count, sum) = match self.values.get(state) {
+                Some(successors) => (*successors.get(&letter).unwrap_or(&0), Champion::get_sum(successors)),
+                None => (0, 0),
+            };
+            let denominator = sum as f64 + smoothing * alphabet_size;
+            log_probability += if denominator > 0.0 {
+                ((count as f64 + smoothing) / denominator).log2()
+            } else {
+                f64::NEG_INFINITY
+            };
+        }
+        (log_probability, splitted.len())
+    }
+
+    /// Checks the chain for states generation could get stuck on or never reach.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    ///
+    /// let mut champion = Champion::new(10);
+    /// champion.feed("hello".to_string());
+    /// assert!(champion.validate().is_valid());
+    /// ```
+    pub fn validate(&self) -> Validation {
+        let start = TERMINATOR.to_string();
+        let mut dead_ends = Vec::new();
+        for (state, successors) in &self.values {
+            for (letter, _) in successors {
+                if *letter == TERMINATOR {
+                    continue;
+                }
+                let next = self.next_state(state, *letter);
+                if !dead_ends.contains(&next) &&
+                    self.values.get(&next).map_or(true, |next| Champion::get_sum(next) == 0) {
+                    dead_ends.push(next);
+                }
+            }
+        }
+
+        //Walking the chain from the start state, everything we did not visit is unreachable.
+        let mut visited : Vec<String> = Vec::new();
+        let mut to_visit : Vec<String> = if self.values.contains_key(&start) {
+            vec![start.clone()]
+        } else {
+            self.values.keys().cloned().collect()
+        };
+        while let Some(state) = to_visit.pop() {
+            if visited.contains(&state) {
+                continue;
+            }
+            if let Some(successors) = self.values.get(&state) {
+                to_visit.extend(successors.keys()
+                                .filter(|letter| **letter != TERMINATOR)
+                                .map(|letter| self.next_state(&state, *letter)));
+            }
+            visited.push(state);
+        }
+        let mut unreachable : Vec<String> = self.values.keys()
+            .filter(|state| **state != start && !visited.contains(state))
+            .cloned()
+            .collect();
+
+        dead_ends.sort();
+        unreachable.sort();
+        Validation {
+            dead_ends : dead_ends,
+            unreachable : unreachable,
+        }
+    }
+
     //WRN: It may not function as expected : If you have "raloud" in your db, contains("oud") will return true.
-    fn contains(&self, nickname : String) -> bool {
-        let splitted = Champion::split_nickname(nickname).unwrap();
-        //We split the nickname and see if for each (0, 1) pair, 1 is a key of the 0 hashmap.
-        for pair in &splitted {
-            match self.values.get(&pair[0]) {
+    fn contains(&self, nickname : &str) -> bool {
+        //We walk the nickname's states and see if for each (state, letter) pair, letter is a key of the state hashmap.
+        let mut state = TERMINATOR.to_string();
+        for letter in nickname.chars().chain(iter::once(TERMINATOR)) {
+            match self.values.get(state.as_str()) {
                 None => return false,
-                Some(value) => if !value.contains_key(&pair[1]){
+                Some(value) => if !value.contains_key(&letter) {
                     return false },
             }
+            Champion::push_letter(&mut state, letter, self.order);
         }
         return true;
     }
@@ -192,16 +643,192 @@ impl Champion {
         self.id
     }
 
+    /// Returns the number of previous letters each letter depends on.
+    pub fn get_order(&self) -> usize {
+        self.order
+    }
+
+    /// Returns `true` if the champion has not been fed yet.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns every `(state, letter, count)` transition of the chain, sorted by state then letter.
+    ///
+    /// The state nicknames start from and the letter ending them are `TERMINATOR`. With an order
+    /// greater than 1, the states at the beginning of a nickname start with it too.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    ///
+    /// let mut champion = Champion::new(10);
+    /// champion.feed("ab");
+    /// assert_eq!(vec![("\0".to_string(), 'a', 1), ("a".to_string(), 'b', 1), ("b".to_string(), '\0', 1)],
+    ///            champion.transitions());
+    /// ```
+    pub fn transitions(&self) -> Vec<(String, char, u64)> {
+        let mut transitions : Vec<(String, char, u64)> = self.values.iter()
+            .flat_map(|(state, successors)| successors.iter().map(move |(&letter, &count)| (state.clone(), letter, count)))
+            .collect();
+        transitions.sort();
+        transitions
+    }
+
+    /// Returns the `(state, letter)` transitions generating `nickname`, the terminating character included.
+    ///
+    /// Lets storage backends update a champion without loading it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    ///
+    /// let champion = Champion::new(10);
+    /// assert_eq!(vec![("\0".to_string(), 'a'), ("a".to_string(), '\0')], champion.nickname_transitions("a"));
+    /// ```
+    pub fn nickname_transitions(&self, nickname : &str) -> Vec<(String, char)> {
+        self.split_nickname(nickname.to_string()).unwrap_or_else(Vec::new)
+    }
+
+    /// Adds `count` occurrences of `letter` after `state`, see `transitions`.
+    ///
+    /// Lets storage backends rebuild a champion transition by transition.
+    pub fn add_transition(&mut self, state : &str, letter : char, count : u64) {
+        if count > 0 {
+            *self.values.entry(state.to_string()).or_insert_with(HashMap::new).entry(letter).or_insert(0) += count;
+        }
+    }
+
+    /// Computes statistics about the data the champion was fed with.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    ///
+    /// let mut champion = Champion::new(10);
+    /// champion.feed("foo".to_string());
+    /// champion.feed("bar".to_string());
+    /// assert_eq!(2, champion.stats().nicknames);
+    /// ```
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats {
+            nicknames : 0,
+            states : 0,
+            transitions : 0,
+            total_count : 0,
+            average_length : 0.0,
+            entropy : HashMap::new(),
+            starts : Vec::new(),
+            ends : Vec::new(),
+        };
+        let start = TERMINATOR.to_string();
+        let mut ends : HashMap<char, u64> = HashMap::new();
+        for (state, successors) in &self.values {
+            let sum = Champion::get_sum(successors);
+            stats.total_count += sum;
+            if *state == start {
+                stats.starts = successors.iter().map(|(letter, count)| (*letter, *count)).collect();
+                continue;
+            }
+            stats.states += 1;
+            stats.transitions += successors.len();
+            if let Some(count) = successors.get(&TERMINATOR) {
+                //Every nickname ends exactly once, even in data stored without starting letters.
+                stats.nicknames += *count;
+                *ends.entry(state.chars().last().unwrap_or(TERMINATOR)).or_insert(0) += *count;
+            }
+            let entropy = successors.values().fold(0f64, |entropy, count| {
+                let p = *count as f64 / sum as f64;
+                if p > 0.0 { entropy - p * p.log2() } else { entropy }
+            });
+            stats.entropy.insert(state.clone(), entropy);
+        }
+        stats.ends = ends.into_iter().collect();
+        if stats.nicknames > 0 {
+            //A nickname of n letters makes n links, plus its starting one if it was recorded.
+            let starting = Champion::get_sum(self.values.get(&start).unwrap_or(&HashMap::new()));
+            stats.average_length = (stats.total_count - starting) as f64 / stats.nicknames as f64;
+        }
+        stats.starts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        stats.ends.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        stats
+    }
+
+    /// Exports the chain as a Graphviz DOT graph.
+    ///
+    /// Nodes are states, plus a `START` and an `END` node. With an order above 1, a `^` marks the beginning of the
+    /// nickname in the states. Edges are labelled with the probability of going from a state to the next one.
+    ///
+    /// # Arguments
+    ///
+    /// * `top` : If set, only the `top` most probable successors of each state are kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    ///
+    /// let mut champion = Champion::new(10);
+    /// champion.feed("foo".to_string());
+    /// println!("{}", champion.to_dot(Some(3)));
+    /// ```
+    pub fn to_dot(&self, top : Option<usize>) -> String {
+        let start = TERMINATOR.to_string();
+        let node = |state : &str| {
+            if state == start {
+                return "\"START\"".to_string();
+            }
+            let escaped : String = state.chars().map(|letter| match letter {
+                TERMINATOR => "^".to_string(),
+                '"' => "\\\"".to_string(),
+                '\\' => "\\\\".to_string(),
+                _ => letter.to_string(),
+            }).collect();
+            format!("\"{}\"", escaped)
+        };
+
+        //HashMaps have no order : we sort everything so that the output is stable.
+        let mut states : Vec<&String> = self.values.keys().collect();
+        states.sort();
+
+        let mut ret = format!("digraph champion_{} {{\n", self.id);
+        ret.push_str("    \"START\" [shape=box];\n    \"END\" [shape=box];\n");
+        for state in states {
+            let successors = &self.values[state];
+            let sum = Champion::get_sum(successors);
+            let mut edges : Vec<(&char, &u64)> = successors.iter().collect();
+            edges.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            if let Some(top) = top {
+                edges.truncate(top);
+            }
+            for (letter, count) in edges {
+                let probability = *count as f64 / sum as f64;
+                let next = match *letter {
+                    TERMINATOR => "\"END\"".to_string(),
+                    letter => node(&self.next_state(state, letter)),
+                };
+                ret.push_str(&format!("    {} -> {} [label=\"{:.3}\", weight={}];\n",
+                                      node(state), next, probability, count));
+            }
+        }
+        ret.push_str("}\n");
+        ret
+    }
+
     //TODO: See what happens if you have the following nickname : "\0".
-    fn split_nickname(nickname : String) -> Option<Vec<Vec<char>>> {
+    fn split_nickname(&self, nickname : String) -> Option<Vec<(String, char)>> {
         if !nickname.is_empty() {
-            //We modify the nickname in order to include a terminating character.
-            let mut null_terminated_nickname = nickname.clone();
-            null_terminated_nickname.push('\0');
-            let chars : Vec<char> = null_terminated_nickname.chars().collect();
-            let mut ret : Vec<Vec<char>> = Vec::new();
-            for pair in chars.windows(2) {
-                ret.push(vec![pair[0], pair[1]])
+            //We start from a state made of a terminating character, and include another one at the end.
+            //The starting one records which letters nicknames start with.
+            let mut state = TERMINATOR.to_string();
+            let mut ret : Vec<(String, char)> = Vec::new();
+            for letter in nickname.chars().chain(iter::once(TERMINATOR)) {
+                let next = self.next_state(&state, letter);
+                ret.push((state, letter));
+                state = next;
             }
             return Some(ret);
         }
@@ -210,8 +837,17 @@ impl Champion {
 
     // Maybe derive traits later ?
     ///Returns a JSON String representing the Champion generation data, __without the champion's id__.
+    ///
+    ///Data of an order 1 chain is kept in the original format, a plain `{state: {letter: count}}` object.
     pub fn serialize(&self) -> Result<String, serde_json::error::Error> {
-        serde_json::to_string(&self.values)
+        if self.order == 1 {
+            return serde_json::to_string(&self.values);
+        }
+        let obj = ObjectBuilder::new()
+            .insert("order", self.order)
+            .insert("values", &self.values)
+            .unwrap();
+        serde_json::to_string(&obj)
     }
     ///Attempts to load the data from a JSON string into the object
     /// # Arguments
@@ -220,9 +856,101 @@ impl Champion {
     ///
     /// # Panics
     ///
-    /// If the JSON string is invalid or malformed.
-    //TODO: Change that to a Result or an option.
+    /// If the JSON string is invalid or malformed, see `try_deserialize`.
     pub fn deserialize(&mut self, json_string : String) {
-        self.values = serde_json::from_str(&json_string).unwrap()
+        self.try_deserialize(&json_string).unwrap();
+    }
+
+    /// Loads the data from a JSON string into the object, as returned by `serialize`.
+    ///
+    /// The object is left untouched if the string is invalid.
+    ///
+    /// # Arguments
+    ///
+    /// * `json_string` : The JSON formatted string to parse
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    ///
+    /// let mut champion = Champion::new(10);
+    /// assert!(champion.try_deserialize("{\"order\": 2").is_err());
+    /// assert!(champion.try_deserialize(r#"{"\u0000": {"a": 1}, "a": {"\u0000": 1}}"#).is_ok());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the string isn't JSON, doesn't hold a chain, or holds a chain of order 0.
+    pub fn try_deserialize(&mut self, json_string : &str) -> Result<(), DeserializeError> {
+        let json : Value = try!(serde_json::from_str(json_string));
+        //States are objects : a number can only be the order of a wrapped chain.
+        let (order, values) = match json.find("order").and_then(|order| order.as_u64()) {
+            Some(order) => (order as usize, json.find("values").cloned().unwrap_or(Value::Null)),
+            None => (1, json),
+        };
+        if order == 0 {
+            return Err(DeserializeError::InvalidOrder);
+        }
+        self.values = try!(serde_json::from_value(values));
+        self.order = order;
+        Ok(())
+    }
+}
+
+/// Iterator over generated nicknames, returned by `Champion::nicknames`.
+pub struct Nicknames<'a> {
+    champion : &'a Champion,
+    max_len : u32,
+    min_len : u32,
+    unique : bool,
+    seen : HashSet<String>,
+    known : HashSet<String>,
+}
+
+impl<'a> Nicknames<'a> {
+    /// Never yields the same nickname twice.
+    pub fn unique(mut self) -> Nicknames<'a> {
+        self.unique = true;
+        self
+    }
+
+    /// Only yields nicknames of at least `min_len` chars, without leading or trailing whitespace.
+    pub fn valid(mut self, min_len : u32) -> Nicknames<'a> {
+        self.min_len = min_len;
+        self
+    }
+
+    /// Never yields one of the `known` nicknames, like the ones the champion was fed with.
+    pub fn novel<I>(mut self, known : I) -> Nicknames<'a> where I : IntoIterator<Item = String> {
+        self.known.extend(known);
+        self
+    }
+
+    fn accepts(&self, nickname : &str) -> bool {
+        if self.min_len > 0 && ((nickname.chars().count() as u32) < self.min_len || nickname.trim() != nickname) {
+            return false;
+        }
+        !self.known.contains(nickname) && !(self.unique && self.seen.contains(nickname))
+    }
+}
+
+impl<'a> Iterator for Nicknames<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        for _ in 0..MAX_REJECTED {
+            let nickname = match self.champion.gen(self.max_len) {
+                Ok(nickname) => nickname,
+                Err(_) => return None,
+            };
+            if self.accepts(&nickname) {
+                if self.unique {
+                    self.seen.insert(nickname.clone());
+                }
+                return Some(nickname);
+            }
+        }
+        None
     }
 }
@@ -0,0 +1,173 @@
+//! Keeps the raw nicknames each champion was fed with, so that its model can be trained again with other parameters.
+//!
+//! # Examples
+//!
+//! ```
+//! use self::zilean::corpus::*;
+//!
+//! let mut corpus = Corpus::new(10);
+//! corpus.add(Entry::new("foo".to_string()));
+//! corpus.add(Entry::new("bar".to_string()));
+//!
+//! let champion = corpus.train(2);
+//! println!("Generated nickname : {}", champion.gen(10).unwrap());
+//! ```
+extern crate serde_json;
+use ::champion::Champion as Champion;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use self::serde_json::Value;
+use self::serde_json::builder::ObjectBuilder;
+
+/// A nickname, and where it was seen for the first time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The nickname itself.
+    pub nickname : String,
+    /// When the nickname was first seen, in seconds since the Unix epoch.
+    pub first_seen : u64,
+    /// The realm the nickname was seen on, like `EUW`.
+    pub realm : Option<String>,
+    /// The game the nickname was seen in.
+    pub game_id : Option<u64>,
+}
+
+impl Entry {
+    /// Creates an entry for a nickname seen right now, in an unknown realm and game.
+    pub fn new(nickname : String) -> Entry {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0);
+        Entry {
+            nickname : nickname,
+            first_seen : now,
+            realm : None,
+            game_id : None,
+        }
+    }
+
+    /// Returns a JSON String representing where the nickname was seen, __without the nickname__.
+    pub fn serialize(&self) -> Result<String, serde_json::Error> {
+        let obj = ObjectBuilder::new()
+            .insert("first_seen", self.first_seen)
+            .insert("realm", self.realm.clone())
+            .insert("game_id", self.game_id)
+            .unwrap();
+        serde_json::to_string(&obj)
+    }
+
+    /// Parses what `serialize` returned for a nickname.
+    ///
+    /// # Arguments
+    ///
+    /// * `nickname` : The nickname the data is about.
+    /// * `json_string` : The JSON formatted string to parse.
+    pub fn deserialize(nickname : String, json_string : &str) -> Result<Entry, serde_json::Error> {
+        let json : Value = try!(serde_json::from_str(json_string));
+        Ok(Entry {
+            nickname : nickname,
+            first_seen : json.find("first_seen").and_then(|first_seen| first_seen.as_u64()).unwrap_or(0),
+            realm : json.find("realm").and_then(|realm| realm.as_str()).map(|realm| realm.to_string()),
+            game_id : json.find("game_id").and_then(|game_id| game_id.as_u64()),
+        })
+    }
+}
+
+/// Every distinct nickname a champion was fed with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corpus {
+    champion_id : u32,
+    entries : HashMap<String, Entry>,
+}
+
+impl Corpus {
+    /// Constructs an empty corpus for a champion.
+    pub fn new(champion_id : u32) -> Corpus {
+        Corpus {
+            champion_id : champion_id,
+            entries : HashMap::new(),
+        }
+    }
+
+    /// Adds a nickname to the corpus.
+    ///
+    /// If the nickname is already known, we only keep the entry that was seen first.
+    ///
+    /// Returns `true` if the nickname was not known yet.
+    pub fn add(&mut self, entry : Entry) -> bool {
+        if entry.nickname.is_empty() {
+            return false;
+        }
+        if let Some(known) = self.entries.get_mut(&entry.nickname) {
+            if entry.first_seen < known.first_seen {
+                *known = entry;
+            }
+            return false;
+        }
+        self.entries.insert(entry.nickname.clone(), entry);
+        true
+    }
+
+    /// Returns the entry of a nickname, if it is in the corpus.
+    pub fn get(&self, nickname : &str) -> Option<&Entry> {
+        self.entries.get(nickname)
+    }
+
+    /// Returns every entry, in the order they were first seen.
+    pub fn entries(&self) -> Vec<&Entry> {
+        let mut entries : Vec<&Entry> = self.entries.values().collect();
+        entries.sort_by(|a, b| a.first_seen.cmp(&b.first_seen).then(a.nickname.cmp(&b.nickname)));
+        entries
+    }
+
+    /// Returns the id of the champion the nicknames belong to.
+    pub fn get_champion_id(&self) -> u32 {
+        self.champion_id
+    }
+
+    /// Returns the number of nicknames in the corpus.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the corpus has no nickname.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Trains a new model from every nickname of the corpus.
+    ///
+    /// # Arguments
+    ///
+    /// * `order` : The order of the chain, see `Champion::with_order`.
+    pub fn train(&self, order : usize) -> Champion {
+        self.train_filtered(order, |_| true)
+    }
+
+    /// Trains a new model from the nicknames of the corpus `filter` accepts.
+    ///
+    /// # Arguments
+    ///
+    /// * `order` : The order of the chain, see `Champion::with_order`.
+    /// * `filter` : Returns `true` for the entries to train with.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::corpus::*;
+    ///
+    /// let mut corpus = Corpus::new(10);
+    /// corpus.add(Entry::new("foo".to_string()));
+    /// corpus.add(Entry::new("a very long nickname".to_string()));
+    ///
+    /// let champion = corpus.train_filtered(1, |entry| entry.nickname.len() <= 16);
+    /// assert_eq!(1, champion.stats().nicknames);
+    /// ```
+    pub fn train_filtered<F>(&self, order : usize, filter : F) -> Champion where F : Fn(&Entry) -> bool {
+        let mut champion = Champion::with_order(self.champion_id, order);
+        for entry in self.entries() {
+            if filter(entry) {
+                champion.feed(&entry.nickname);
+            }
+        }
+        champion
+    }
+}
@@ -1,4 +1,6 @@
-//! Handles a Redis database and enables storing and loading champions from it.
+//! Handles a database and enables storing and loading champions from it.
+//!
+//! The storage backend is chosen by the scheme of the database URL, see `store` for the available ones.
 //!
 //! # Examples
 //!
@@ -22,25 +24,77 @@
 //! use self::zilean::champion::*;
 //! let db = Database::new("redis://127.0.0.1/".to_string());
 //!
-//! let champion = db.load(10);
+//! let champion = db.load(10).unwrap();
 //! ```
-extern crate redis;
-extern crate serde_json;
+use ::bulk::{BulkError, BulkReader};
 use ::champion::Champion as Champion;
+use ::corpus::{Corpus, Entry};
+use ::registry::ChampionRegistry as ChampionRegistry;
+use ::store::{ChampionStore, ConnectError, StoreError};
+use ::store::file_store::FileStore as FileStore;
+use ::store::memory_store::MemoryStore as MemoryStore;
+use ::store::redis_store::RedisStore as RedisStore;
+use ::store::sqlite_store::SqliteStore as SqliteStore;
+use ::training::train_parallel;
 use std::collections::HashMap;
-use self::redis::Commands;
 
-///Represents a connection to the Redis database.
+//How many times `Database::update` tries before giving up with a conflict.
+const UPDATE_ATTEMPTS : usize = 10;
+
+///Represents a connection to the database, whatever the storage backend.
+///
+///It can be shared between threads, behind an `Arc`.
 pub struct Database {
     url : String,
-    con : redis::Connection,
+    store : Box<ChampionStore>,
 }
 impl Database {
-    /// Creates a new Database object, connected to a redis instance.
+    /// Connects to a database, using the backend matching the URL scheme.
     ///
     /// # Arguments
     ///
-    /// * `champion_id` : the id of the champion.
+    /// * `url` : the database URL. `redis://` URLs connect to a redis instance, keeping keys in the namespace given by
+    /// the `namespace` parameter (`redis://127.0.0.1/?namespace=zelkow:v1` is the default), `file:///some/directory`
+    /// stores files in a directory, `sqlite:///some/file.sqlite` uses an SQLite database and `memory://` keeps
+    /// everything in memory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::database::*;
+    /// use self::zilean::store::ConnectError;
+    ///
+    /// let db = Database::connect("memory://".to_string()).unwrap();
+    ///
+    /// match Database::connect("radis://127.0.0.1".to_string()) {
+    ///     Err(ConnectError::InvalidUrl(_)) => (),
+    ///     _ => panic!("radis isn't a backend"),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidUrl` : the URL is malformed, or its scheme is unknown.
+    /// * `Unreachable` : the redis instance doesn't answer, or the directory or the file can't be opened.
+    /// * `AuthenticationFailed` : the redis instance refused the credentials of the URL.
+    pub fn connect(url : String) -> Result<Database, ConnectError> {
+        let store : Box<ChampionStore> = if url.starts_with("redis://") {
+            Box::new(try!(RedisStore::connect(&url)))
+        } else if url.starts_with("file://") {
+            Box::new(try!(FileStore::open(&url["file://".len()..])
+                .map_err(|err| ConnectError::Unreachable(StoreError::Io(err)))))
+        } else if url.starts_with("sqlite://") {
+            Box::new(try!(SqliteStore::open(&url["sqlite://".len()..])
+                .map_err(|err| ConnectError::Unreachable(StoreError::Sqlite(err)))))
+        } else if url.starts_with("memory://") {
+            Box::new(MemoryStore::new())
+        } else {
+            return Err(ConnectError::InvalidUrl(format!("Unknown scheme in {:?}", url)));
+        };
+        Ok(Database::with_store(url, store))
+    }
+
+    /// Same as `connect`, panicking on errors.
     ///
     /// # Example
     ///
@@ -52,33 +106,32 @@ impl Database {
     ///
     /// # Panics
     ///
-    /// If we aren't able to connect to the redis instance. (That's bad)
+    /// If the URL is malformed, or if we aren't able to connect to the redis instance or to open the directory or the
+    /// file. (That's bad)
     pub fn new(url : String) -> Database {
-        let con;
-        match Database::connect(&url) {
-            Ok(client) => con = client,
-            Err(_) => panic!("Error trying to connect to database using URL {:?}", url),
-        }
-        Database {
-            url : url,
-            con : con,
+        match Database::connect(url.clone()) {
+            Ok(db) => db,
+            Err(err) => panic!("Error trying to connect to database using URL {:?} : {}", url, err),
         }
     }
 
-    fn connect(url : &String) -> redis::RedisResult<redis::Connection> {
-        let redis_url;
-        match redis::parse_redis_url(url) {
-            Ok(url) => redis_url=url,
-            Err(url) => panic!("Malformed Database URL : {:?}", url),
+    /// Creates a new Database object on top of an already opened storage backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` : the URL describing the storage, returned by `get_url`.
+    /// * `store` : the storage backend.
+    pub fn with_store(url : String, store : Box<ChampionStore>) -> Database {
+        Database {
+            url : url,
+            store : store,
         }
-        let client = try!(redis::Client::open(redis_url));
-        let con = try!(client.get_connection());
-        Ok(con)
     }
 
     /// Loads a Champion from the database.
     ///
-    /// If the said champion could not be found, then no data is loaded and an empty Champion object is returned.
+    /// Returns `None` if the champion was never stored. A stored champion may still be empty, if it was stored before
+    /// being fed.
     ///
     /// # Arguments
     ///
@@ -90,21 +143,21 @@ impl Database {
     /// use self::zilean::database::*;
     ///
     /// let db = Database::new("redis://127.0.0.1".to_string());
-    /// let champion = db.load(10);
-    /// ```
-    //maybe return Some
-    pub fn load(&self, champion_id : u32) -> Champion {
-        let mut champion = Champion::new(champion_id);
-        match self.con.get(champion_id) {
-            Ok(val) => {
-                champion.deserialize(val);
-                return champion;
-            },
-            Err(_) => return champion,
-        }
+    /// match db.load(10) {
+    ///     Ok(Some(champion)) => println!("{}", champion.gen(16).unwrap()),
+    ///     Ok(None) => println!("No such champion"),
+    ///     Err(err) => println!("{}", err),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// A `Corrupt` error if the stored data can't be parsed, other errors if the storage failed to answer.
+    pub fn load(&self, champion_id : u32) -> Result<Option<Champion>, StoreError> {
+        self.store.load(champion_id)
     }
 
-    /// Attempts to store a champion into the redis database.
+    /// Attempts to store a champion into the database.
     ///
     /// # Arguments
     ///
@@ -121,29 +174,415 @@ impl Database {
     /// champion.feed("Hello".to_string());
     /// db.store(&champion);
     /// ```
+    pub fn store(&self, champion : &Champion) -> Result<(), StoreError> {
+        self.store.store(champion)
+    }
+
+    /// Stores a champion only if the stored one is still `expected`, `None` meaning that it must not be stored yet.
+    ///
+    /// Returns `false`, writing nothing, if another writer, maybe in another process, changed the champion since
+    /// `expected` was loaded. See `update` to try again until it succeeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `expected` : the champion as it was loaded.
+    /// * `champion` : the champion to store.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    /// use self::zilean::database::*;
     ///
-    /// # Panics
+    /// let db = Database::new("memory://".to_string());
+    /// let mut champion = Champion::new(10);
+    /// assert!(db.compare_and_store(None, &champion).unwrap());
+    ///
+    /// champion.feed("foo");
+    /// //Another writer stored the champion since it was loaded empty.
+    /// assert!(!db.compare_and_store(None, &champion).unwrap());
+    /// assert!(db.compare_and_store(Some(&Champion::new(10)), &champion).unwrap());
+    /// ```
+    pub fn compare_and_store(&self, expected : Option<&Champion>, champion : &Champion) -> Result<bool, StoreError> {
+        self.store.compare_and_store(expected, champion)
+    }
+
+    /// Loads a champion, computes its new value with `f`, and stores it, unless another writer changed it in between.
+    ///
+    /// When that happens, the champion is loaded again and `f` called again, so `f` may run several times. It gets
+    /// `None` if the champion isn't stored, and returns `None` to leave the champion as it is.
+    ///
+    /// Returns the stored champion, or `None` if `f` did.
+    ///
+    /// # Arguments
+    ///
+    /// * `champion_id` : the id of the champion.
+    /// * `f` : computes the new champion from the stored one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    /// use self::zilean::database::*;
+    ///
+    /// let db = Database::new("memory://".to_string());
+    /// db.store(&Champion::with_order(10, 2)).unwrap();
+    ///
+    /// //Trains the champion again with the same order.
+    /// let champion = db.update(10, |current| current.map(|current| {
+    ///     let mut champion = Champion::with_order(10, current.get_order());
+    ///     champion.feed("foo");
+    ///     champion
+    /// })).unwrap();
+    /// assert_eq!(champion, db.load(10).unwrap());
+    /// ```
     ///
-    /// If it's unable to serialize the champion into JSON data.
-    pub fn store(&self, champion : &Champion) -> redis::RedisResult<()> {
-        match champion.serialize() {
-            Ok(val) => self.con.set(champion.get_id(), val),
-            Err(val) => panic!(val),
+    /// # Errors
+    ///
+    /// A `Conflict` error if the champion kept being changed by other writers, other errors like `load`.
+    pub fn update<F>(&self, champion_id : u32, mut f : F) -> Result<Option<Champion>, StoreError>
+        where F : FnMut(Option<&Champion>) -> Option<Champion> {
+        for _ in 0..UPDATE_ATTEMPTS {
+            let current = try!(self.load(champion_id));
+            let champion = match f(current.as_ref()) {
+                Some(champion) => champion,
+                None => return Ok(None),
+            };
+            if try!(self.compare_and_store(current.as_ref(), &champion)) {
+                return Ok(Some(champion));
+            }
         }
+        Err(StoreError::Conflict(champion_id))
+    }
+
+    /// Returns the ids of every stored champion, sorted.
+    ///
+    /// With Redis, keys are walked with `SCAN`, which doesn't block the instance like `KEYS` would.
+    pub fn list(&self) -> Result<Vec<u32>, StoreError> {
+        self.store.list()
+    }
+
+    /// Returns `true` if the champion is stored, even if its data is corrupt.
+    ///
+    /// # Arguments
+    ///
+    /// `champion_id` : the id of the champion.
+    pub fn exists(&self, champion_id : u32) -> Result<bool, StoreError> {
+        self.store.exists(champion_id)
+    }
+
+    /// Deletes a champion and its corpus.
+    ///
+    /// Returns `true` if the champion was stored.
+    ///
+    /// # Arguments
+    ///
+    /// `champion_id` : the id of the champion.
+    pub fn delete(&self, champion_id : u32) -> Result<bool, StoreError> {
+        self.store.delete(champion_id)
+    }
+
+    /// Forgets what a champion learnt, storing it empty with the same chain order.
+    ///
+    /// Its corpus is kept, so that `rebuild` can train it again.
+    ///
+    /// Returns `true` if the champion was stored, nothing is written otherwise. What is fed while resetting is forgotten
+    /// too, rather than overwritten.
+    ///
+    /// # Arguments
+    ///
+    /// `champion_id` : the id of the champion.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::champion::*;
+    /// use self::zilean::database::*;
+    ///
+    /// let db = Database::new("memory://".to_string());
+    /// let mut champion = Champion::with_order(10, 2);
+    /// champion.feed("foo");
+    /// db.store(&champion).unwrap();
+    ///
+    /// assert!(db.reset(10).unwrap());
+    /// assert_eq!(Some(Champion::with_order(10, 2)), db.load(10).unwrap());
+    /// ```
+    pub fn reset(&self, champion_id : u32) -> Result<bool, StoreError> {
+        let reset = try!(self.update(champion_id, |current| {
+            current.map(|champion| Champion::with_order(champion_id, champion.get_order()))
+        }));
+        Ok(reset.is_some())
+    }
+
+    /// Loads the nicknames a champion was fed with.
+    ///
+    /// If the said champion could not be found, an empty corpus is returned. Entries that can't be parsed are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// `champion_id` : the id of the champion.
+    pub fn load_corpus(&self, champion_id : u32) -> Corpus {
+        self.store.load_corpus(champion_id).unwrap_or_else(|_| Corpus::new(champion_id))
+    }
+
+    /// Loads the corpus of a champion, like `load_corpus`, but fails if the storage fails to answer.
+    ///
+    /// # Arguments
+    ///
+    /// `champion_id` : the id of the champion.
+    pub fn try_load_corpus(&self, champion_id : u32) -> Result<Corpus, StoreError> {
+        self.store.load_corpus(champion_id)
     }
 
-    ///Returns the redis instance URL.
+    /// Adds nicknames to the corpus of a champion.
+    ///
+    /// Nicknames already seen keep their first entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `champion_id` : the id of the champion.
+    /// * `entries` : the nicknames to add.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use self::zilean::corpus::*;
+    /// use self::zilean::database::*;
+    ///
+    /// let db = Database::new("redis://127.0.0.1".to_string());
+    /// db.add_to_corpus(10, &vec![Entry::new("Hello".to_string())]);
+    /// ```
+    pub fn add_to_corpus(&self, champion_id : u32, entries : &[Entry]) -> Result<(), StoreError> {
+        self.store.add_to_corpus(champion_id, entries)
+    }
+
+    /// Feeds nicknames to a champion and adds them to its corpus.
+    ///
+    /// Unlike loading the champion, feeding it and storing it back, concurrent feeds of the same champion can't lose
+    /// each other's updates. With Redis, the champion isn't even loaded.
+    ///
+    /// Returns the number of nicknames the champion learnt something from, see `Champion::feed`.
+    ///
+    /// # Arguments
+    ///
+    /// * `champion_id` : the id of the champion.
+    /// * `entries` : the nicknames to feed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use self::zilean::corpus::*;
+    /// use self::zilean::database::*;
+    ///
+    /// let db = Database::new("redis://127.0.0.1".to_string());
+    /// db.feed(10, &vec![Entry::new("Hello".to_string())]).unwrap();
+    /// ```
+    pub fn feed(&self, champion_id : u32, entries : &[Entry]) -> Result<usize, StoreError> {
+        self.store.feed(champion_id, entries)
+    }
+
+    /// Trains a champion again from its corpus, with another chain order, and stores it in place of the current one.
+    ///
+    /// # Arguments
+    ///
+    /// * `champion_id` : the id of the champion.
+    /// * `order` : the order of the new chain, see `Champion::with_order`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use self::zilean::database::*;
+    ///
+    /// let db = Database::new("redis://127.0.0.1".to_string());
+    /// let champion = db.rebuild(10, 2).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the corpus can't be loaded, the champion is left unchanged. Otherwise like `update`.
+    pub fn rebuild(&self, champion_id : u32, order : usize) -> Result<Champion, StoreError> {
+        self.rebuild_filtered(champion_id, order, |_| true)
+    }
+
+    /// Same as `rebuild`, only training with the entries `filter` accepts.
+    pub fn rebuild_filtered<F>(&self, champion_id : u32, order : usize, filter : F) -> Result<Champion, StoreError>
+        where F : Fn(&Entry) -> bool {
+        //The corpus is loaded again on conflicts, to train with the nicknames fed in the meantime. If it can't be
+        //loaded, the champion is left as it is rather than replaced by an empty one.
+        let mut failure = None;
+        let champion = try!(self.update(champion_id, |_| {
+            match self.try_load_corpus(champion_id) {
+                Ok(corpus) => Some(corpus.train_filtered(order, &filter)),
+                Err(err) => {
+                    failure = Some(err);
+                    None
+                },
+            }
+        }));
+        match failure {
+            Some(err) => Err(err),
+            None => Ok(champion.unwrap()),
+        }
+    }
+
+    /// Loads many champions at once, in a single request.
+    ///
+    /// Champions that were never stored are returned empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `champion_ids` : the ids of the champions.
+    ///
+    /// # Errors
+    ///
+    /// Like `load`, if the data of one of the champions is corrupt or if the storage failed to answer.
+    pub fn load_many(&self, champion_ids : &[u32]) -> Result<Vec<Champion>, StoreError> {
+        let champions = try!(self.store.load_many(champion_ids));
+        Ok(champion_ids.iter().zip(champions).map(|(&champion_id, champion)| {
+            champion.unwrap_or_else(|| Champion::new(champion_id))
+        }).collect())
+    }
+
+    /// Feeds many champions at once, for instance when backfilling thousands of games.
+    ///
+    /// Champions are loaded in a single request, fed in parallel on `workers` threads, and stored with their nicknames
+    /// in a single transaction. A trained champion is only stored if nobody wrote it since it was loaded : champions
+    /// written in the meantime are loaded and trained again, and if they keep being written, their nicknames are fed
+    /// to the stored champion like `feed` does, so that concurrent feeds and resets are never overwritten.
+    ///
+    /// Returns the trained champions, sorted by id.
+    ///
+    /// # Arguments
+    ///
+    /// * `bulk` : the nicknames to feed, by champion id.
+    /// * `workers` : the number of threads feeding champions.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use self::zilean::corpus::*;
+    /// use self::zilean::database::*;
+    /// use std::collections::HashMap;
+    ///
+    /// let db = Database::new("redis://127.0.0.1".to_string());
+    /// let mut bulk = HashMap::new();
+    /// bulk.insert(10, vec![Entry::new("foo".to_string())]);
+    /// bulk.insert(11, vec![Entry::new("bar".to_string())]);
+    /// let champions = db.train_bulk(bulk, 4).unwrap();
+    /// ```
+    pub fn train_bulk(&self, bulk : HashMap<u32, Vec<Entry>>, workers : usize) -> Result<Vec<Champion>, StoreError> {
+        let mut champion_ids : Vec<u32> = bulk.keys().cloned().collect();
+        //Trained champions come back sorted by id, in the order of the loaded ones.
+        champion_ids.sort();
+        let mut champions = Vec::new();
+        for _ in 0..UPDATE_ATTEMPTS {
+            if champion_ids.is_empty() {
+                break;
+            }
+            //Champions that can't be loaded aren't replaced by freshly trained ones.
+            let loaded = try!(self.store.load_many(&champion_ids));
+            let jobs = champion_ids.iter().zip(&loaded).map(|(&champion_id, champion)| {
+                let champion = champion.clone().unwrap_or_else(|| Champion::new(champion_id));
+                let nicknames = bulk[&champion_id].iter().map(|entry| entry.nickname.clone()).collect();
+                (champion, nicknames)
+            }).collect();
+            let batch : Vec<(Option<Champion>, Champion)> = loaded.into_iter().zip(train_parallel(jobs, workers))
+                .collect();
+            let entries = champion_ids.iter().map(|&champion_id| (champion_id, bulk[&champion_id].clone())).collect();
+
+            let conflicts = try!(self.store.compare_and_store_batch(&batch, &entries));
+            champions.extend(batch.into_iter().map(|(_, champion)| champion)
+                .filter(|champion| !conflicts.contains(&champion.get_id())));
+            champion_ids = conflicts;
+        }
+
+        //Still written by others : what was trained is dropped, the nicknames are fed to the stored champions.
+        for &champion_id in &champion_ids {
+            try!(self.store.feed(champion_id, &bulk[&champion_id]));
+        }
+        for (&champion_id, champion) in champion_ids.iter().zip(try!(self.store.load_many(&champion_ids))) {
+            champions.push(champion.unwrap_or_else(|| Champion::new(champion_id)));
+        }
+        champions.sort_by_key(|champion| champion.get_id());
+        Ok(champions)
+    }
+
+    /// Stores champions and adds nicknames to their corpus, in a single transaction when the storage allows it.
+    ///
+    /// Unlike `train_bulk`, the champions aren't fed the nicknames, they are stored as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `champions` : the champions to store.
+    /// * `entries` : the nicknames to add, by champion id.
+    pub fn store_batch(&self, champions : &[Champion], entries : &HashMap<u32, Vec<Entry>>) -> Result<(), StoreError> {
+        self.store.store_batch(champions, entries)
+    }
+
+    /// Loads the champion registry from the database.
+    ///
+    /// Returns `None` if no registry was stored, or if the stored one can't be parsed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use self::zilean::database::*;
+    ///
+    /// let db = Database::new("redis://127.0.0.1".to_string());
+    /// let registry = db.load_registry();
+    /// ```
+    pub fn load_registry(&self) -> Option<ChampionRegistry> {
+        self.store.load_registry().unwrap_or(None)
+    }
+
+    /// Loads the champion registry, like `load_registry`, but fails if the storage fails to answer, and with a `Corrupt`
+    /// error if the stored registry can't be parsed.
+    pub fn try_load_registry(&self) -> Result<Option<ChampionRegistry>, StoreError> {
+        self.store.load_registry()
+    }
+
+    /// Stores the champion registry into the database, replacing the previous one.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry` : A reference to the registry to be stored.
+    pub fn store_registry(&self, registry : &ChampionRegistry) -> Result<(), StoreError> {
+        self.store.store_registry(registry)
+    }
+
+    /// Moves data written by older versions to the layout of this one, like Redis keys stored without namespace.
+    ///
+    /// Data already in the current layout is never overwritten, and with Redis, keys which don't hold data of an older
+    /// version are left alone. It is meant to run once, when upgrading : other applications sharing the instance could
+    /// still have keys looking like legacy ones.
+    ///
+    /// Returns the number of items moved.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use self::zilean::database::*;
+    ///
+    /// let db = Database::new("redis://127.0.0.1/?namespace=zelkow:v1".to_string());
+    /// let moved = db.migrate().unwrap();
+    /// ```
+    pub fn migrate(&self) -> Result<usize, StoreError> {
+        self.store.migrate()
+    }
+
+    ///Returns the database URL.
     pub fn get_url(&self) -> String {
         self.url.clone()
     }
     //breaks <S>OLID ?
-    //TODO: Err handling
     /// Deserializes data sent by Twitch, the python script that sends game data.
     ///
     /// # Arguments
     ///
     /// * `json_string` : The string sent by Twitch.
     ///
+    /// Champion ids appearing more than once have their nicknames merged, see `::bulk::BulkReader`.
+    ///
     /// #Example
     ///
     /// ```rust,ignore
@@ -152,14 +591,18 @@ impl Database {
     /// let twitch_data : String;
     /// twitch_data = r#"{"14" : ["foo", "bar", "quux"], "81" : ["hello", "world"]}"#.to_string();
     /// // Assuming we put the String in this var.
-    /// let deserialized_data = Database::deserialize_bulk(twitch_data);
+    /// let deserialized_data = Database::deserialize_bulk(twitch_data).unwrap();
     /// ```
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If the data aren't deserializable.
-    pub fn deserialize_bulk(json_string : String) -> HashMap<u32, Vec<String>> {
-        let json_hm : HashMap<u32, Vec<String>> = serde_json::from_str(&json_string).unwrap();
-        return json_hm
+    /// A `BulkError::Syntax` if the data isn't a `{champion_id: [nicknames]}` object.
+    pub fn deserialize_bulk(json_string : String) -> Result<HashMap<u32, Vec<String>>, BulkError> {
+        let mut bulk = HashMap::new();
+        for pair in BulkReader::new(json_string.as_bytes()) {
+            let (champion_id, nicknames) = try!(pair);
+            bulk.entry(champion_id).or_insert_with(Vec::new).extend(nicknames);
+        }
+        Ok(bulk)
     }
 }
@@ -0,0 +1,37 @@
+//! Measures how far two nicknames are from each other.
+//!
+//! # Examples
+//!
+//! ```
+//! use self::zilean::distance::*;
+//!
+//! assert_eq!(1, edit_distance("Yasuo", "Yasou"));
+//! ```
+use std::cmp;
+
+/// Returns the number of typos needed to get from `a` to `b`.
+///
+/// A typo is inserting, removing or replacing a char, or swapping two neighbouring ones.
+pub fn edit_distance(a : &str, b : &str) -> usize {
+    let a : Vec<char> = a.chars().collect();
+    let b : Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in 0..a.len() + 1 {
+        distances[i][0] = i;
+    }
+    for j in 0..b.len() + 1 {
+        distances[0][j] = j;
+    }
+    for i in 1..a.len() + 1 {
+        for j in 1..b.len() + 1 {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = cmp::min(distances[i - 1][j - 1] + cost,
+                                        cmp::min(distances[i - 1][j], distances[i][j - 1]) + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = cmp::min(distance, distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
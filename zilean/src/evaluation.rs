@@ -0,0 +1,145 @@
+//! Measures how good the nicknames generated from a corpus are, to compare chain orders and smoothing settings.
+//!
+//! The corpus is split in two : a model is trained on the first part, and we check how likely it finds the nicknames
+//! of the second part (perplexity). We also look at the nicknames it generates : how many of them were never seen
+//! (novelty) and how far they are from real nicknames (edit distance).
+//!
+//! # Examples
+//!
+//! ```
+//! use self::zilean::corpus::*;
+//! use self::zilean::evaluation::*;
+//!
+//! let mut corpus = Corpus::new(10);
+//! for nickname in vec!["foo", "bar", "baz", "quux", "foobar", "barbaz"] {
+//!     corpus.add(Entry::new(nickname.to_string()));
+//! }
+//!
+//! for order in 1..4 {
+//!     let mut parameters = Parameters::default();
+//!     parameters.order = order;
+//!     let report = evaluate(&corpus, &parameters);
+//!     println!("order {} : perplexity {}", order, report.perplexity);
+//! }
+//! ```
+extern crate rand;
+use ::corpus::Corpus as Corpus;
+use ::distance::edit_distance;
+use std::collections::HashSet;
+use self::rand::{Rng, SeedableRng, StdRng};
+
+/// How to split the corpus, train the model and sample nicknames from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameters {
+    /// The order of the chain, see `Champion::with_order`.
+    pub order : usize,
+    /// The additive smoothing used to compute the perplexity, see `Champion::log_probability`.
+    pub smoothing : f64,
+    /// The share of the corpus kept out of training, between 0 and 1.
+    pub test_ratio : f64,
+    /// The number of nicknames to generate.
+    pub samples : usize,
+    /// The maximum length of the generated nicknames.
+    pub max_len : u32,
+    /// Seeds the split, so that two evaluations test on the same nicknames.
+    pub seed : usize,
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            order : 1,
+            smoothing : 0.1,
+            test_ratio : 0.2,
+            samples : 100,
+            max_len : 16,
+            seed : 0,
+        }
+    }
+}
+
+/// Result of `evaluate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// The number of nicknames the model was trained on.
+    pub train_size : usize,
+    /// The number of nicknames kept out of training.
+    pub test_size : usize,
+    /// Per letter perplexity of the test nicknames, the lower the better. Infinite without smoothing if a test
+    /// nickname can't be generated.
+    pub perplexity : f64,
+    /// The number of nicknames generated.
+    pub generated : usize,
+    /// The share of generated nicknames that are not in the training set.
+    pub novelty_rate : f64,
+    /// The average edit distance between a generated nickname and the closest nickname of the corpus.
+    pub average_distance : f64,
+}
+
+/// Splits a corpus in a training corpus and a test corpus.
+///
+/// # Arguments
+///
+/// * `corpus` : The corpus to split.
+/// * `test_ratio` : The share of the nicknames going to the test corpus, between 0 and 1.
+/// * `seed` : The same seed always splits a corpus the same way.
+pub fn split(corpus : &Corpus, test_ratio : f64, seed : usize) -> (Corpus, Corpus) {
+    let mut entries = corpus.entries();
+    let mut rng : StdRng = SeedableRng::from_seed(&[seed][..]);
+    rng.shuffle(&mut entries);
+
+    let test_size = (entries.len() as f64 * test_ratio.max(0.0).min(1.0)).round() as usize;
+    let mut train = Corpus::new(corpus.get_champion_id());
+    let mut test = Corpus::new(corpus.get_champion_id());
+    for (i, entry) in entries.into_iter().enumerate() {
+        if i < test_size {
+            test.add(entry.clone());
+        } else {
+            train.add(entry.clone());
+        }
+    }
+    (train, test)
+}
+
+/// Evaluates the model trained on a corpus with the given parameters.
+///
+/// # Arguments
+///
+/// * `corpus` : Every nickname we have for the champion.
+/// * `parameters` : How to split, train and sample.
+pub fn evaluate(corpus : &Corpus, parameters : &Parameters) -> Report {
+    let (train, test) = split(corpus, parameters.test_ratio, parameters.seed);
+    let champion = train.train(parameters.order);
+
+    let (log_probability, letters) = test.entries().iter().fold((0f64, 0usize), |(total, letters), entry| {
+        let (log_probability, count) = champion.log_probability(&entry.nickname, parameters.smoothing);
+        (total + log_probability, letters + count)
+    });
+    let perplexity = if letters > 0 { (-log_probability / letters as f64).exp2() } else { 0.0 };
+
+    let known : HashSet<&str> = train.entries().into_iter().map(|entry| entry.nickname.as_str()).collect();
+    let real : Vec<&str> = corpus.entries().into_iter().map(|entry| entry.nickname.as_str()).collect();
+    let mut generated = 0;
+    let mut novel = 0;
+    let mut total_distance = 0;
+    for _ in 0..parameters.samples {
+        let nickname = match champion.gen(parameters.max_len) {
+            Ok(nickname) => nickname,
+            Err(_) => break,
+        };
+        generated += 1;
+        if !known.contains(nickname.as_str()) {
+            novel += 1;
+        }
+        total_distance += real.iter().map(|real| edit_distance(&nickname, real)).min().unwrap_or(0);
+    }
+
+    Report {
+        train_size : train.len(),
+        test_size : test.len(),
+        perplexity : perplexity,
+        generated : generated,
+        novelty_rate : if generated > 0 { novel as f64 / generated as f64 } else { 0.0 },
+        average_distance : if generated > 0 { total_distance as f64 / generated as f64 } else { 0.0 },
+    }
+}
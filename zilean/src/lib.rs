@@ -1,2 +1,11 @@
+pub mod bulk;
+pub mod cache;
 pub mod champion;
+pub mod corpus;
 pub mod database;
+pub mod distance;
+pub mod evaluation;
+pub mod registry;
+pub mod snapshot;
+pub mod store;
+pub mod training;
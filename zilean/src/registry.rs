@@ -0,0 +1,241 @@
+//! Maps champion ids to their keys, names and titles, using Riot's static data.
+//!
+//! # Examples
+//!
+//! ```
+//! use self::zilean::registry::*;
+//!
+//! let static_data = r#"{"type": "champion", "version": "6.5.1", "data": {
+//!     "157": {"id": 157, "key": "Yasuo", "name": "Yasuo", "title": "the Unforgiven"}
+//! }}"#;
+//! let registry = ChampionRegistry::from_static_data(static_data).unwrap();
+//!
+//! assert_eq!("Yasuo", registry.get(157).unwrap().name);
+//! assert_eq!(Some(157), registry.id_by_key("Yasuo"));
+//! ```
+extern crate serde_json;
+use ::distance::edit_distance;
+use std::cmp;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use self::serde_json::Value;
+use self::serde_json::builder::ObjectBuilder;
+
+//Number of champions suggested when a name is ambiguous or unknown.
+const MAX_SUGGESTIONS : usize = 5;
+
+/// Errors that can happen while loading static data.
+#[derive(Debug)]
+pub enum RegistryError {
+    /// The data isn't valid JSON.
+    Json(serde_json::Error),
+    /// The JSON doesn't follow Riot's static data format.
+    Format(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RegistryError::Json(ref err) => write!(f, "Invalid static data : {}", err),
+            RegistryError::Format(ref err) => write!(f, "Malformed static data : {}", err),
+        }
+    }
+}
+
+impl error::Error for RegistryError {
+    fn description(&self) -> &str {
+        match *self {
+            RegistryError::Json(_) => "invalid static data",
+            RegistryError::Format(_) => "malformed static data",
+        }
+    }
+}
+
+impl From<serde_json::Error> for RegistryError {
+    fn from(err : serde_json::Error) -> RegistryError {
+        RegistryError::Json(err)
+    }
+}
+
+/// What Riot's static data tells about a champion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChampionInfo {
+    /// The numeric id, as used by the database.
+    pub id : u32,
+    /// The internal name, like `DrMundo`.
+    pub key : String,
+    /// The display name, like `Dr. Mundo`.
+    pub name : String,
+    /// The title, like `the Madman of Zaun`.
+    pub title : String,
+}
+
+/// Every champion known by the static data, indexed by id and by key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChampionRegistry {
+    version : String,
+    champions : HashMap<u32, ChampionInfo>,
+    keys : HashMap<String, u32>,
+}
+
+impl ChampionRegistry {
+    /// Parses static data in the format of `twitch/dbg_champs.json`.
+    ///
+    /// # Arguments
+    ///
+    /// * `json_string` : The static data, as returned by Riot's API with `dataById=true`.
+    pub fn from_static_data(json_string : &str) -> Result<ChampionRegistry, RegistryError> {
+        let json : Value = try!(serde_json::from_str(json_string));
+        let version = json.find("version").and_then(|version| version.as_str()).unwrap_or("").to_string();
+        let data = match json.find("data").and_then(|data| data.as_object()) {
+            Some(data) => data,
+            None => return Err(RegistryError::Format("no data object".to_string())),
+        };
+
+        let mut registry = ChampionRegistry {
+            version : version,
+            champions : HashMap::new(),
+            keys : HashMap::new(),
+        };
+        for (entry, champion) in data {
+            let field = |name : &str| {
+                champion.find(name).and_then(|value| value.as_str()).map(|value| value.to_string())
+                    .ok_or_else(|| RegistryError::Format(format!("champion {} has no {}", entry, name)))
+            };
+            let id = match champion.find("id").and_then(|id| id.as_u64()) {
+                Some(id) => id as u32,
+                None => return Err(RegistryError::Format(format!("champion {} has no id", entry))),
+            };
+            registry.insert(ChampionInfo {
+                id : id,
+                key : try!(field("key")),
+                name : try!(field("name")),
+                title : try!(field("title")),
+            });
+        }
+        Ok(registry)
+    }
+
+    /// Adds or replaces a champion.
+    pub fn insert(&mut self, champion : ChampionInfo) {
+        if let Some(previous) = self.champions.remove(&champion.id) {
+            self.keys.remove(&previous.key);
+        }
+        self.keys.insert(champion.key.clone(), champion.id);
+        self.champions.insert(champion.id, champion);
+    }
+
+    /// Returns the champion with this id, if it exists.
+    pub fn get(&self, id : u32) -> Option<&ChampionInfo> {
+        self.champions.get(&id)
+    }
+
+    /// Returns `true` if a champion has this id.
+    pub fn contains(&self, id : u32) -> bool {
+        self.champions.contains_key(&id)
+    }
+
+    /// Returns the id of the champion with this key, like `DrMundo`.
+    pub fn id_by_key(&self, key : &str) -> Option<u32> {
+        self.keys.get(key).cloned()
+    }
+
+    /// Returns the id of the champion with exactly this display name, like `Dr. Mundo`.
+    pub fn id_by_name(&self, name : &str) -> Option<u32> {
+        self.champions.values().find(|champion| champion.name == name).map(|champion| champion.id)
+    }
+
+    /// Finds a champion by its name or key, the way a human would type it.
+    ///
+    /// Case, spaces and punctuation are ignored, so `"drmundo"` and `"Dr. Mundo"` both match. If nothing matches
+    /// exactly, a name containing the query (`"mundo"`) or close enough to it (`"yasou"`) is accepted, as long as only
+    /// one champion qualifies.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` : The name to look for.
+    ///
+    /// # Errors
+    ///
+    /// The closest champions, best first, if no single champion matches. It is empty if nothing comes close.
+    pub fn find_by_name(&self, query : &str) -> Result<&ChampionInfo, Vec<&ChampionInfo>> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return Err(Vec::new());
+        }
+        let champions = self.champions();
+
+        let exact : Vec<&ChampionInfo> = champions.iter().cloned()
+            .filter(|champion| normalize(&champion.name) == query || normalize(&champion.key) == query)
+            .collect();
+        if exact.len() == 1 {
+            return Ok(exact[0]);
+        }
+
+        let partial : Vec<&ChampionInfo> = champions.iter().cloned()
+            .filter(|champion| normalize(&champion.name).contains(&query) || normalize(&champion.key).contains(&query))
+            .collect();
+        match partial.len() {
+            1 => return Ok(partial[0]),
+            0 => (),
+            _ => return Err(partial.into_iter().take(MAX_SUGGESTIONS).collect()),
+        }
+
+        //Typos : we rank every champion by edit distance and only keep the ones that are close enough.
+        let max_distance = cmp::max(1, query.chars().count() / 3);
+        let mut close : Vec<(usize, &ChampionInfo)> = champions.iter().cloned()
+            .map(|champion| {
+                let distance = cmp::min(edit_distance(&normalize(&champion.name), &query),
+                                        edit_distance(&normalize(&champion.key), &query));
+                (distance, champion)
+            })
+            .filter(|&(distance, _)| distance <= max_distance)
+            .collect();
+        close.sort_by_key(|&(distance, champion)| (distance, champion.id));
+        if close.len() == 1 || (close.len() > 1 && close[0].0 < close[1].0) {
+            return Ok(close[0].1);
+        }
+        Err(close.into_iter().take(MAX_SUGGESTIONS).map(|(_, champion)| champion).collect())
+    }
+
+    /// Returns every known champion, sorted by id.
+    pub fn champions(&self) -> Vec<&ChampionInfo> {
+        let mut champions : Vec<&ChampionInfo> = self.champions.values().collect();
+        champions.sort_by_key(|champion| champion.id);
+        champions
+    }
+
+    /// Returns the number of known champions.
+    pub fn len(&self) -> usize {
+        self.champions.len()
+    }
+
+    /// Returns the version of the static data.
+    pub fn get_version(&self) -> String {
+        self.version.clone()
+    }
+
+    /// Returns the registry as static data, so that it can be parsed back by `from_static_data`.
+    pub fn serialize(&self) -> Result<String, serde_json::Error> {
+        let data = self.champions().iter().fold(ObjectBuilder::new(), |builder, champion| {
+            builder.insert_object(champion.id.to_string(), |obj| {
+                obj.insert("id", champion.id)
+                    .insert("key", champion.key.clone())
+                    .insert("name", champion.name.clone())
+                    .insert("title", champion.title.clone())
+            })
+        }).unwrap();
+        let obj = ObjectBuilder::new()
+            .insert("type", "champion")
+            .insert("version", self.version.clone())
+            .insert("data", data)
+            .unwrap();
+        serde_json::to_string(&obj)
+    }
+}
+
+//Lowercase letters and digits only : "Kha'Zix" becomes "khazix".
+fn normalize(name : &str) -> String {
+    name.chars().filter(|letter| letter.is_alphanumeric()).flat_map(|letter| letter.to_lowercase()).collect()
+}
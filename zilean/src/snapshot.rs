@@ -0,0 +1,289 @@
+//! Exports a whole database to a single portable file, and imports it back, to move models between environments.
+//!
+//! A snapshot is a JSON object holding a manifest, the champion registry, and every champion with its corpus :
+//!
+//! ```text
+//! {
+//!     "manifest": {"format": "zelkow-snapshot", "version": 1, "created": 1476871200},
+//!     "registry": {"type": "champion", "version": "6.5.1", "data": {...}},
+//!     "champions": [
+//!         {"id": 157, "chain": {"\u0000": {"f": 1}, ...}, "corpus": {"foo": {"first_seen": 1476871200, ...}}}
+//!     ]
+//! }
+//! ```
+//!
+//! Chains and corpus entries are in the format of `Champion::serialize` and `Entry::serialize`, and the registry is
+//! static data, so a snapshot doesn't depend on the storage backend it was taken from.
+//!
+//! # Examples
+//!
+//! ```
+//! use self::zilean::champion::*;
+//! use self::zilean::database::*;
+//! use self::zilean::snapshot::*;
+//!
+//! let staging = Database::new("memory://".to_string());
+//! let mut champion = Champion::new(10);
+//! champion.feed("foo");
+//! staging.store(&champion).unwrap();
+//!
+//! let mut file = Vec::new();
+//! export(&staging, &mut file).unwrap();
+//!
+//! let production = Database::new("memory://".to_string());
+//! import(&production, &file[..], ImportMode::Merge).unwrap();
+//! assert_eq!(Some(champion), production.load(10).unwrap());
+//! ```
+extern crate serde_json;
+use ::champion::Champion as Champion;
+use ::corpus::Entry as Entry;
+use ::database::Database as Database;
+use ::registry::ChampionRegistry as ChampionRegistry;
+use ::store::StoreError as StoreError;
+use std::collections::{BTreeMap, HashMap};
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::u32;
+use self::serde_json::Value;
+use self::serde_json::builder::ObjectBuilder;
+
+/// Identifies snapshot files, in their manifest.
+pub const FORMAT : &'static str = "zelkow-snapshot";
+/// The version of the snapshot format written by `export`. `import` reads this version and the previous ones.
+pub const VERSION : u64 = 1;
+
+/// Errors that can happen while exporting or importing a snapshot.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The snapshot file couldn't be read or written.
+    Io(io::Error),
+    /// The snapshot isn't JSON, or something couldn't be converted to JSON.
+    Json(serde_json::Error),
+    /// The database failed to answer.
+    Store(StoreError),
+    /// The snapshot is JSON, but not a valid snapshot.
+    Invalid(String),
+    /// The snapshot was written by a newer version.
+    UnsupportedVersion(u64),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnapshotError::Io(ref err) => write!(f, "I/O error : {}", err),
+            SnapshotError::Json(ref err) => write!(f, "JSON error : {}", err),
+            SnapshotError::Store(ref err) => write!(f, "{}", err),
+            SnapshotError::Invalid(ref err) => write!(f, "Invalid snapshot : {}", err),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "Snapshot version {} is newer than the supported version {}", version, VERSION)
+            },
+        }
+    }
+}
+
+impl error::Error for SnapshotError {
+    fn description(&self) -> &str {
+        match *self {
+            SnapshotError::Io(_) => "i/o error",
+            SnapshotError::Json(_) => "json error",
+            SnapshotError::Store(_) => "store error",
+            SnapshotError::Invalid(_) => "invalid snapshot",
+            SnapshotError::UnsupportedVersion(_) => "unsupported snapshot version",
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err : io::Error) -> SnapshotError {
+        SnapshotError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(err : serde_json::Error) -> SnapshotError {
+        SnapshotError::Json(err)
+    }
+}
+
+impl From<StoreError> for SnapshotError {
+    fn from(err : StoreError) -> SnapshotError {
+        SnapshotError::Store(err)
+    }
+}
+
+/// How `import` treats what the database already holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Champions of the snapshot replace the stored ones with the same id, other stored champions are kept. Corpora
+    /// are merged, stored entries winning, and the registry is only imported if none is stored.
+    Merge,
+    /// The snapshot is stored, then the champions it doesn't hold are deleted, so that the database ends up holding the
+    /// champions of the snapshot. Their corpora are merged like with `Merge`. The stored registry is replaced, if the
+    /// snapshot holds one.
+    Replace,
+}
+
+/// What a snapshot held, returned by `export` and `import`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SnapshotStats {
+    /// The number of champions.
+    pub champions : usize,
+    /// The number of corpus entries, over every champion.
+    pub nicknames : usize,
+    /// Whether the snapshot held a registry.
+    pub registry : bool,
+}
+
+/// Writes every champion of the database, with its corpus, and the registry to `writer`.
+///
+/// Champions are written one at a time, the database is never loaded in memory at once. Champions deleted while the
+/// snapshot is written are left out.
+///
+/// # Arguments
+///
+/// * `db` : the database to export.
+/// * `writer` : where the snapshot is written, like a file.
+///
+/// # Errors
+///
+/// If the database fails to answer, if a champion is corrupt, or if writing fails. The snapshot is incomplete then.
+pub fn export<W>(db : &Database, mut writer : W) -> Result<SnapshotStats, SnapshotError> where W : Write {
+    let champion_ids = try!(db.list());
+    let registry = try!(db.try_load_registry());
+    let created = SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0);
+    let mut stats = SnapshotStats {
+        registry : registry.is_some(),
+        .. SnapshotStats::default()
+    };
+    let registry = match registry {
+        Some(registry) => try!(serde_json::from_str(&try!(registry.serialize()))),
+        None => Value::Null,
+    };
+
+    //The manifest comes first, so that readers can check the version before anything else. It doesn't count the
+    //champions : some may be deleted before they are written.
+    let manifest = ObjectBuilder::new()
+        .insert("format", FORMAT)
+        .insert("version", VERSION)
+        .insert("created", created)
+        .unwrap();
+    try!(write!(writer, "{{\"manifest\": {},\n\"registry\": {},\n\"champions\": [",
+                try!(serde_json::to_string(&manifest)), try!(serde_json::to_string(&registry))));
+    for champion_id in champion_ids {
+        let champion = match try!(db.load(champion_id)) {
+            Some(champion) => champion,
+            //Deleted since it was listed.
+            None => continue,
+        };
+        let corpus = try!(db.try_load_corpus(champion_id));
+        let mut entries = BTreeMap::new();
+        for entry in corpus.entries() {
+            let val : Value = try!(serde_json::from_str(&try!(entry.serialize())));
+            entries.insert(entry.nickname.clone(), val);
+        }
+        stats.nicknames += entries.len();
+        let chain : Value = try!(serde_json::from_str(&try!(champion.serialize())));
+        let obj = ObjectBuilder::new()
+            .insert("id", champion_id)
+            .insert("chain", chain)
+            .insert("corpus", entries)
+            .unwrap();
+        try!(write!(writer, "{}\n{}", if stats.champions == 0 { "" } else { "," }, try!(serde_json::to_string(&obj))));
+        stats.champions += 1;
+    }
+    try!(write!(writer, "]}}\n"));
+    try!(writer.flush());
+    Ok(stats)
+}
+
+/// Reads a snapshot written by `export` from `reader`, and stores it in the database.
+///
+/// The whole snapshot is checked before anything is written, so an invalid snapshot leaves the database untouched.
+/// Nothing is deleted before the snapshot is stored, so a failing import never leaves the database empty.
+///
+/// # Arguments
+///
+/// * `db` : the database to import into.
+/// * `reader` : where the snapshot is read from, like a file.
+/// * `mode` : how the champions already stored are treated.
+///
+/// # Errors
+///
+/// If the snapshot can't be read, is invalid, or was written by a newer version, or if the database fails to answer.
+pub fn import<R>(db : &Database, reader : R, mode : ImportMode) -> Result<SnapshotStats, SnapshotError> where R : Read {
+    let snapshot : Value = try!(serde_json::from_reader(reader));
+    let manifest = try!(snapshot.find("manifest").ok_or(invalid("no manifest")));
+    if manifest.find("format").and_then(|format| format.as_str()) != Some(FORMAT) {
+        return Err(invalid(format!("the format isn't {:?}", FORMAT)));
+    }
+    match manifest.find("version").and_then(|version| version.as_u64()) {
+        Some(version) if version > VERSION => return Err(SnapshotError::UnsupportedVersion(version)),
+        Some(_) => (),
+        None => return Err(invalid("no version")),
+    }
+
+    let registry = match snapshot.find("registry") {
+        Some(&Value::Null) | None => None,
+        Some(registry) => Some(try!(ChampionRegistry::from_static_data(&try!(serde_json::to_string(registry)))
+            .map_err(|err| invalid(format!("invalid registry : {}", err))))),
+    };
+    let mut champions = Vec::new();
+    let mut corpora = HashMap::new();
+    let mut stats = SnapshotStats {
+        registry : registry.is_some(),
+        .. SnapshotStats::default()
+    };
+    let items = try!(snapshot.find("champions").and_then(|champions| champions.as_array())
+        .ok_or(invalid("no champions")));
+    for item in items {
+        let champion_id = try!(item.find("id").and_then(|id| id.as_u64()).ok_or(invalid("a champion has no id")));
+        if champion_id > u32::MAX as u64 {
+            return Err(invalid(format!("champion id {} is too large", champion_id)));
+        }
+        let champion_id = champion_id as u32;
+        if corpora.contains_key(&champion_id) {
+            return Err(invalid(format!("champion {} appears more than once", champion_id)));
+        }
+        let mut champion = Champion::new(champion_id);
+        let chain = try!(serde_json::to_string(try!(item.find("chain").ok_or(invalid("a champion has no chain")))));
+        try!(champion.try_deserialize(&chain).map_err(|err| invalid(format!("champion {} : {}", champion_id, err))));
+        let mut entries = Vec::new();
+        if let Some(corpus) = item.find("corpus").and_then(|corpus| corpus.as_object()) {
+            for (nickname, val) in corpus {
+                let entry = Entry::deserialize(nickname.clone(), &try!(serde_json::to_string(val)));
+                entries.push(try!(entry.map_err(|err| invalid(format!("champion {} : {:?} : {}", champion_id, nickname, err)))));
+            }
+        }
+        stats.nicknames += entries.len();
+        champions.push(champion);
+        corpora.insert(champion_id, entries);
+    }
+    stats.champions = champions.len();
+
+    if let Some(registry) = registry {
+        //A stored registry that can't be parsed is replaced, like a missing one.
+        let stored = match db.try_load_registry() {
+            Ok(stored) => stored.is_some(),
+            Err(StoreError::Corrupt(_)) => false,
+            Err(err) => return Err(From::from(err)),
+        };
+        if mode == ImportMode::Replace || !stored {
+            try!(db.store_registry(&registry));
+        }
+    }
+    try!(db.store_batch(&champions, &corpora));
+    if mode == ImportMode::Replace {
+        for champion_id in try!(db.list()) {
+            if !corpora.contains_key(&champion_id) {
+                try!(db.delete(champion_id));
+            }
+        }
+    }
+    Ok(stats)
+}
+
+fn invalid<S>(err : S) -> SnapshotError where S : Into<String> {
+    SnapshotError::Invalid(err.into())
+}
@@ -0,0 +1,344 @@
+//! Stores champions in a directory, for small deployments without Redis.
+//!
+//! The directory holds one file per champion and per corpus, the registry, and an index of the stored champions :
+//!
+//! ```text
+//! index.json
+//! lock
+//! registry.json
+//! champions/<id>.json
+//! corpus/<id>.json
+//! ```
+//!
+//! Every file is written to a temporary file first, then renamed over the previous one, so that a crash never leaves a
+//! half written file behind.
+//!
+//! Writers hold a lock on the `lock` file of the directory, so that processes sharing it write one at a time and never
+//! overwrite each other's updates. On platforms without `flock`, only the threads of a process are kept apart.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use self::zilean::database::*;
+//!
+//! let db = Database::new("file:///var/lib/zelkow".to_string());
+//! let champion = db.load(10).unwrap();
+//! ```
+#[cfg(unix)]
+extern crate libc;
+extern crate serde_json;
+use ::champion::Champion as Champion;
+use ::corpus::{Corpus, Entry};
+use ::registry::ChampionRegistry as ChampionRegistry;
+use ::store::{ChampionStore, StoreError};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Mutex, MutexGuard};
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use self::serde_json::Value;
+
+//Makes temporary file names unique within the process.
+static TMP_COUNTER : AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Champions stored in a directory.
+pub struct FileStore {
+    root : PathBuf,
+    //Held while writing, so that threads of the process don't write between what `compare_and_store` or `feed` read
+    //and what they write, nor lose each other's index and corpus updates.
+    writing : Mutex<()>,
+    //Locked while writing too, to keep other processes out. A flock is shared by the threads of a process, hence the
+    //mutex.
+    lock_file : File,
+}
+
+//Returned by FileStore::lock, releases the lock file when dropped.
+struct WriteGuard<'a> {
+    _writing : MutexGuard<'a, ()>,
+    lock_file : &'a File,
+}
+
+impl<'a> Drop for WriteGuard<'a> {
+    fn drop(&mut self) {
+        //Closing the file would release it too, this only fails if the file isn't locked.
+        let _ = flock(self.lock_file, false);
+    }
+}
+
+//Locks or unlocks a file for every process, waiting for other processes to unlock it.
+#[cfg(unix)]
+fn flock(file : &File, lock : bool) -> io::Result<()> {
+    let operation = if lock { libc::LOCK_EX } else { libc::LOCK_UN };
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn flock(_ : &File, _ : bool) -> io::Result<()> {
+    Ok(())
+}
+
+impl FileStore {
+    /// Opens a directory, creating it if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` : the directory, like `/var/lib/zelkow`.
+    pub fn open<P>(root : P) -> io::Result<FileStore> where P : AsRef<Path> {
+        let root = root.as_ref().to_path_buf();
+        try!(fs::create_dir_all(root.join("champions")));
+        try!(fs::create_dir_all(root.join("corpus")));
+        let lock_file = try!(OpenOptions::new().write(true).create(true).open(root.join("lock")));
+        Ok(FileStore {
+            root : root,
+            writing : Mutex::new(()),
+            lock_file : lock_file,
+        })
+    }
+
+    //Keeps other threads and other processes from writing until the guard is dropped.
+    fn lock(&self) -> Result<WriteGuard, StoreError> {
+        //Nothing is guarded, a panicking thread can't leave anything inconsistent.
+        let writing = match self.writing.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        try!(flock(&self.lock_file, true));
+        Ok(WriteGuard {
+            _writing : writing,
+            lock_file : &self.lock_file,
+        })
+    }
+
+    fn champion_path(&self, champion_id : u32) -> PathBuf {
+        self.root.join("champions").join(format!("{}.json", champion_id))
+    }
+
+    fn corpus_path(&self, champion_id : u32) -> PathBuf {
+        self.root.join("corpus").join(format!("{}.json", champion_id))
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.root.join("index.json")
+    }
+
+    fn registry_path(&self) -> PathBuf {
+        self.root.join("registry.json")
+    }
+
+    //Returns None if the file doesn't exist.
+    fn read(path : &Path) -> io::Result<Option<String>> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let mut content = String::new();
+        try!(file.read_to_string(&mut content));
+        Ok(Some(content))
+    }
+
+    //Writes a temporary file next to `path`, then renames it over `path`.
+    fn write(path : &Path, content : &str) -> io::Result<()> {
+        let tmp = path.with_extension(format!("{}.{}.tmp", process::id(), TMP_COUNTER.fetch_add(1, Ordering::SeqCst)));
+        let written = File::create(&tmp).and_then(|mut file| {
+            try!(file.write_all(content.as_bytes()));
+            file.sync_all()
+        });
+        match written.and_then(|_| fs::rename(&tmp, path)) {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                let _ = fs::remove_file(&tmp);
+                Err(err)
+            },
+        }
+    }
+
+    fn remove(path : &Path) -> io::Result<bool> {
+        match fs::remove_file(path) {
+            Ok(()) => Ok(true),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn read_index(&self) -> Result<Vec<u32>, StoreError> {
+        match try!(FileStore::read(&self.index_path())) {
+            Some(content) => Ok(try!(serde_json::from_str(&content))),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    //Adds and removes ids from the index, only writing it if it changed.
+    fn update_index(&self, added : &[u32], removed : &[u32]) -> Result<(), StoreError> {
+        let index = try!(self.read_index());
+        let mut updated : Vec<u32> = index.iter().cloned().chain(added.iter().cloned())
+            .filter(|champion_id| !removed.contains(champion_id))
+            .collect();
+        updated.sort();
+        updated.dedup();
+        if updated != index {
+            try!(FileStore::write(&self.index_path(), &try!(serde_json::to_string(&updated))));
+        }
+        Ok(())
+    }
+
+    fn read_corpus(&self, champion_id : u32) -> Result<BTreeMap<String, Value>, StoreError> {
+        match try!(FileStore::read(&self.corpus_path(champion_id))) {
+            Some(content) => Ok(try!(serde_json::from_str(&content))),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    //Adds the entries whose nickname isn't in the corpus yet. The caller holds the lock.
+    fn write_entries(&self, champion_id : u32, entries : &[Entry]) -> Result<(), StoreError> {
+        let mut corpus = try!(self.read_corpus(champion_id));
+        let mut changed = false;
+        for entry in entries {
+            if !corpus.contains_key(&entry.nickname) {
+                let val : Value = try!(serde_json::from_str(&try!(entry.serialize())));
+                corpus.insert(entry.nickname.clone(), val);
+                changed = true;
+            }
+        }
+        if changed {
+            try!(FileStore::write(&self.corpus_path(champion_id), &try!(serde_json::to_string(&corpus))));
+        }
+        Ok(())
+    }
+}
+
+impl ChampionStore for FileStore {
+    fn load(&self, champion_id : u32) -> Result<Option<Champion>, StoreError> {
+        let content = match try!(FileStore::read(&self.champion_path(champion_id))) {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+        let mut champion = Champion::new(champion_id);
+        try!(champion.try_deserialize(&content).map_err(|err| ::store::corrupt(champion_id, err)));
+        Ok(Some(champion))
+    }
+
+    fn store(&self, champion : &Champion) -> Result<(), StoreError> {
+        let content = try!(champion.serialize());
+        let _writing = try!(self.lock());
+        try!(FileStore::write(&self.champion_path(champion.get_id()), &content));
+        self.update_index(&[champion.get_id()], &[])
+    }
+
+    fn compare_and_store(&self, expected : Option<&Champion>, champion : &Champion) -> Result<bool, StoreError> {
+        let content = try!(champion.serialize());
+        let _writing = try!(self.lock());
+        if try!(self.load(champion.get_id())).as_ref() != expected {
+            return Ok(false);
+        }
+        try!(FileStore::write(&self.champion_path(champion.get_id()), &content));
+        try!(self.update_index(&[champion.get_id()], &[]));
+        Ok(true)
+    }
+
+    fn compare_and_store_batch(&self, batch : &[(Option<Champion>, Champion)], entries : &HashMap<u32, Vec<Entry>>)
+        -> Result<Vec<u32>, StoreError> {
+        let mut contents = Vec::new();
+        for &(_, ref champion) in batch {
+            contents.push(try!(champion.serialize()));
+        }
+        let _writing = try!(self.lock());
+        let mut current = Vec::new();
+        for &(_, ref champion) in batch {
+            current.push(try!(self.load(champion.get_id())));
+        }
+        let conflicts = ::store::conflicts(batch, &current);
+        let mut champion_ids = Vec::new();
+        for (&(_, ref champion), content) in batch.iter().zip(contents) {
+            if !conflicts.contains(&champion.get_id()) {
+                try!(FileStore::write(&self.champion_path(champion.get_id()), &content));
+                champion_ids.push(champion.get_id());
+            }
+        }
+        try!(self.update_index(&champion_ids, &[]));
+        for (&champion_id, entries) in entries {
+            if !conflicts.contains(&champion_id) {
+                try!(self.write_entries(champion_id, entries));
+            }
+        }
+        Ok(conflicts)
+    }
+
+    fn list(&self) -> Result<Vec<u32>, StoreError> {
+        self.read_index()
+    }
+
+    fn exists(&self, champion_id : u32) -> Result<bool, StoreError> {
+        Ok(self.champion_path(champion_id).is_file())
+    }
+
+    fn delete(&self, champion_id : u32) -> Result<bool, StoreError> {
+        let _writing = try!(self.lock());
+        let deleted = try!(FileStore::remove(&self.champion_path(champion_id)));
+        try!(FileStore::remove(&self.corpus_path(champion_id)));
+        try!(self.update_index(&[], &[champion_id]));
+        Ok(deleted)
+    }
+
+    fn load_corpus(&self, champion_id : u32) -> Result<Corpus, StoreError> {
+        let mut corpus = Corpus::new(champion_id);
+        for (nickname, val) in try!(self.read_corpus(champion_id)) {
+            if let Ok(entry) = serde_json::to_string(&val).and_then(|val| Entry::deserialize(nickname, &val)) {
+                corpus.add(entry);
+            }
+        }
+        Ok(corpus)
+    }
+
+    fn add_to_corpus(&self, champion_id : u32, entries : &[Entry]) -> Result<(), StoreError> {
+        let _writing = try!(self.lock());
+        self.write_entries(champion_id, entries)
+    }
+
+    //The lock is held from loading to storing, so that concurrent feeds of the process can't lose updates.
+    fn feed(&self, champion_id : u32, entries : &[Entry]) -> Result<usize, StoreError> {
+        let _writing = try!(self.lock());
+        let mut champion = try!(self.load(champion_id)).unwrap_or_else(|| Champion::new(champion_id));
+        let fed = champion.feed_all(entries.iter().map(|entry| entry.nickname.as_str()));
+        try!(FileStore::write(&self.champion_path(champion_id), &try!(champion.serialize())));
+        try!(self.update_index(&[champion_id], &[]));
+        try!(self.write_entries(champion_id, entries));
+        Ok(fed)
+    }
+
+    fn load_registry(&self) -> Result<Option<ChampionRegistry>, StoreError> {
+        let content = try!(FileStore::read(&self.registry_path()));
+        match content {
+            Some(content) => Ok(Some(try!(::store::parse_registry(&content)))),
+            None => Ok(None),
+        }
+    }
+
+    fn store_registry(&self, registry : &ChampionRegistry) -> Result<(), StoreError> {
+        let content = try!(registry.serialize());
+        let _writing = try!(self.lock());
+        Ok(try!(FileStore::write(&self.registry_path(), &content)))
+    }
+
+    fn store_many(&self, champions : &[Champion]) -> Result<(), StoreError> {
+        //The index is only written once.
+        let _writing = try!(self.lock());
+        for champion in champions {
+            try!(FileStore::write(&self.champion_path(champion.get_id()), &try!(champion.serialize())));
+        }
+        let champion_ids : Vec<u32> = champions.iter().map(|champion| champion.get_id()).collect();
+        self.update_index(&champion_ids, &[])
+    }
+}
@@ -0,0 +1,204 @@
+//! Keeps champions in memory, for tests and for running without any external service.
+//!
+//! Everything is kept serialized, like the Redis backend does, so that both behave the same way. Data is lost when the
+//! store is dropped.
+//!
+//! # Examples
+//!
+//! ```
+//! use self::zilean::champion::*;
+//! use self::zilean::database::*;
+//!
+//! let db = Database::new("memory://".to_string());
+//! let mut champion = Champion::new(10);
+//! champion.feed("foo");
+//! db.store(&champion).unwrap();
+//!
+//! assert_eq!(Some(champion), db.load(10).unwrap());
+//! ```
+use ::champion::Champion as Champion;
+use ::corpus::{Corpus, Entry};
+use ::registry::ChampionRegistry as ChampionRegistry;
+use ::store::{ChampionStore, StoreError};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+#[derive(Default)]
+struct Data {
+    champions : HashMap<u32, String>,
+    corpora : HashMap<u32, HashMap<String, String>>,
+    registry : Option<String>,
+}
+
+/// Champions kept in memory.
+pub struct MemoryStore {
+    data : Mutex<Data>,
+}
+
+impl MemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> MemoryStore {
+        MemoryStore {
+            data : Mutex::new(Data::default()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<Data> {
+        //Everything is serialized before being written, so a thread panicking while holding the lock can't leave it
+        //inconsistent.
+        match self.data.lock() {
+            Ok(data) => data,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn parse(champion_id : u32, val : &str) -> Result<Champion, StoreError> {
+        let mut champion = Champion::new(champion_id);
+        try!(champion.try_deserialize(val).map_err(|err| ::store::corrupt(champion_id, err)));
+        Ok(champion)
+    }
+
+    fn serialize_entries(entries : &[Entry]) -> Result<Vec<(String, String)>, StoreError> {
+        let mut values = Vec::new();
+        for entry in entries {
+            values.push((entry.nickname.clone(), try!(entry.serialize())));
+        }
+        Ok(values)
+    }
+}
+
+impl Data {
+    //Nicknames already in the corpus keep their entry.
+    fn add_entries(&mut self, champion_id : u32, values : Vec<(String, String)>) {
+        let corpus = self.corpora.entry(champion_id).or_insert_with(HashMap::new);
+        for (nickname, val) in values {
+            corpus.entry(nickname).or_insert(val);
+        }
+    }
+}
+
+impl ChampionStore for MemoryStore {
+    fn load(&self, champion_id : u32) -> Result<Option<Champion>, StoreError> {
+        match self.lock().champions.get(&champion_id) {
+            Some(val) => Ok(Some(try!(MemoryStore::parse(champion_id, val)))),
+            None => Ok(None),
+        }
+    }
+
+    fn store(&self, champion : &Champion) -> Result<(), StoreError> {
+        let val = try!(champion.serialize());
+        self.lock().champions.insert(champion.get_id(), val);
+        Ok(())
+    }
+
+    fn compare_and_store(&self, expected : Option<&Champion>, champion : &Champion) -> Result<bool, StoreError> {
+        let val = try!(champion.serialize());
+        //The lock is held from comparing to storing, so that nobody can write in between.
+        let mut data = self.lock();
+        let current = match data.champions.get(&champion.get_id()) {
+            Some(current) => Some(try!(MemoryStore::parse(champion.get_id(), current))),
+            None => None,
+        };
+        if current.as_ref() != expected {
+            return Ok(false);
+        }
+        data.champions.insert(champion.get_id(), val);
+        Ok(true)
+    }
+
+    fn compare_and_store_batch(&self, batch : &[(Option<Champion>, Champion)], entries : &HashMap<u32, Vec<Entry>>)
+        -> Result<Vec<u32>, StoreError> {
+        let mut vals = Vec::new();
+        for &(_, ref champion) in batch {
+            vals.push(try!(champion.serialize()));
+        }
+        let mut values = HashMap::new();
+        for (&champion_id, entries) in entries {
+            values.insert(champion_id, try!(MemoryStore::serialize_entries(entries)));
+        }
+        //The lock is held from comparing to storing, so that nobody can write in between.
+        let mut data = self.lock();
+        let mut current = Vec::new();
+        for &(_, ref champion) in batch {
+            current.push(match data.champions.get(&champion.get_id()) {
+                Some(val) => Some(try!(MemoryStore::parse(champion.get_id(), val))),
+                None => None,
+            });
+        }
+        let conflicts = ::store::conflicts(batch, &current);
+        for (&(_, ref champion), val) in batch.iter().zip(vals) {
+            if !conflicts.contains(&champion.get_id()) {
+                data.champions.insert(champion.get_id(), val);
+            }
+        }
+        for (champion_id, values) in values {
+            if !conflicts.contains(&champion_id) {
+                data.add_entries(champion_id, values);
+            }
+        }
+        Ok(conflicts)
+    }
+
+    fn list(&self) -> Result<Vec<u32>, StoreError> {
+        let mut champion_ids : Vec<u32> = self.lock().champions.keys().cloned().collect();
+        champion_ids.sort();
+        Ok(champion_ids)
+    }
+
+    fn exists(&self, champion_id : u32) -> Result<bool, StoreError> {
+        Ok(self.lock().champions.contains_key(&champion_id))
+    }
+
+    fn delete(&self, champion_id : u32) -> Result<bool, StoreError> {
+        let mut data = self.lock();
+        data.corpora.remove(&champion_id);
+        Ok(data.champions.remove(&champion_id).is_some())
+    }
+
+    fn load_corpus(&self, champion_id : u32) -> Result<Corpus, StoreError> {
+        let mut corpus = Corpus::new(champion_id);
+        if let Some(entries) = self.lock().corpora.get(&champion_id) {
+            for (nickname, val) in entries {
+                if let Ok(entry) = Entry::deserialize(nickname.clone(), val) {
+                    corpus.add(entry);
+                }
+            }
+        }
+        Ok(corpus)
+    }
+
+    fn add_to_corpus(&self, champion_id : u32, entries : &[Entry]) -> Result<(), StoreError> {
+        //Entries are serialized before locking, so that nothing is written if one of them can't be.
+        let values = try!(MemoryStore::serialize_entries(entries));
+        self.lock().add_entries(champion_id, values);
+        Ok(())
+    }
+
+    fn feed(&self, champion_id : u32, entries : &[Entry]) -> Result<usize, StoreError> {
+        let values = try!(MemoryStore::serialize_entries(entries));
+        //The lock is held from loading to storing, so that concurrent feeds can't lose updates.
+        let mut data = self.lock();
+        let mut champion = match data.champions.get(&champion_id) {
+            Some(val) => try!(MemoryStore::parse(champion_id, val)),
+            None => Champion::new(champion_id),
+        };
+        let fed = champion.feed_all(entries.iter().map(|entry| entry.nickname.as_str()));
+        let val = try!(champion.serialize());
+        data.champions.insert(champion_id, val);
+        data.add_entries(champion_id, values);
+        Ok(fed)
+    }
+
+    fn load_registry(&self) -> Result<Option<ChampionRegistry>, StoreError> {
+        match self.lock().registry {
+            Some(ref val) => Ok(Some(try!(::store::parse_registry(val)))),
+            None => Ok(None),
+        }
+    }
+
+    fn store_registry(&self, registry : &ChampionRegistry) -> Result<(), StoreError> {
+        let val = try!(registry.serialize());
+        self.lock().registry = Some(val);
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+//! Where champions, their corpus and the champion registry are kept.
+//!
+//! `Database` only talks to a `ChampionStore`, so that the storage can be swapped without touching the code using it.
+//! Each backend lives in its own module.
+extern crate redis;
+extern crate rusqlite;
+extern crate serde_json;
+use ::champion::Champion as Champion;
+use ::corpus::{Corpus, Entry};
+use ::registry::ChampionRegistry as ChampionRegistry;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::io;
+
+pub mod file_store;
+pub mod memory_store;
+pub mod redis_pool;
+pub mod redis_store;
+pub mod sqlite_store;
+
+/// Errors that can happen while reading or writing a store.
+#[derive(Debug)]
+pub enum StoreError {
+    /// The Redis server failed to answer.
+    Redis(redis::RedisError),
+    /// Something couldn't be converted to or from JSON.
+    Json(serde_json::Error),
+    /// A file couldn't be read or written.
+    Io(io::Error),
+    /// The SQLite database failed to answer.
+    Sqlite(rusqlite::Error),
+    /// Stored data can't be parsed.
+    Corrupt(String),
+    /// The champion with this id kept being modified by other writers while being updated.
+    Conflict(u32),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StoreError::Redis(ref err) => write!(f, "Redis error : {}", err),
+            StoreError::Json(ref err) => write!(f, "JSON error : {}", err),
+            StoreError::Io(ref err) => write!(f, "I/O error : {}", err),
+            StoreError::Sqlite(ref err) => write!(f, "SQLite error : {}", err),
+            StoreError::Corrupt(ref err) => write!(f, "Corrupt data : {}", err),
+            StoreError::Conflict(champion_id) => write!(f, "Champion {} was modified concurrently", champion_id),
+        }
+    }
+}
+
+impl error::Error for StoreError {
+    fn description(&self) -> &str {
+        match *self {
+            StoreError::Redis(_) => "redis error",
+            StoreError::Json(_) => "json error",
+            StoreError::Io(_) => "i/o error",
+            StoreError::Sqlite(_) => "sqlite error",
+            StoreError::Corrupt(_) => "corrupt data",
+            StoreError::Conflict(_) => "concurrent modification",
+        }
+    }
+}
+
+impl From<redis::RedisError> for StoreError {
+    fn from(err : redis::RedisError) -> StoreError {
+        StoreError::Redis(err)
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(err : serde_json::Error) -> StoreError {
+        StoreError::Json(err)
+    }
+}
+
+impl From<io::Error> for StoreError {
+    fn from(err : io::Error) -> StoreError {
+        StoreError::Io(err)
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(err : rusqlite::Error) -> StoreError {
+        StoreError::Sqlite(err)
+    }
+}
+
+//The error for a champion whose stored data can't be parsed.
+fn corrupt<E>(champion_id : u32, err : E) -> StoreError where E : fmt::Display {
+    StoreError::Corrupt(format!("champion {} : {}", champion_id, err))
+}
+
+//Returns the ids of the champions of a `compare_and_store_batch` batch that aren't stored as expected, given what
+//is stored, in the order of the batch.
+fn conflicts(batch : &[(Option<Champion>, Champion)], current : &[Option<Champion>]) -> Vec<u32> {
+    batch.iter().zip(current).filter(|&(&(ref expected, _), current)| current != expected)
+        .map(|(&(_, ref champion), _)| champion.get_id())
+        .collect()
+}
+
+//Parses a stored registry, which is a `Corrupt` error if it can't be parsed.
+fn parse_registry(data : &str) -> Result<ChampionRegistry, StoreError> {
+    ChampionRegistry::from_static_data(data).map_err(|err| StoreError::Corrupt(format!("registry : {}", err)))
+}
+
+/// Errors that can happen while opening a store.
+#[derive(Debug)]
+pub enum ConnectError {
+    /// The URL is malformed, or its scheme isn't a known backend.
+    InvalidUrl(String),
+    /// The storage can't be reached : nobody answers at the Redis address, or the directory or the file can't be
+    /// opened.
+    Unreachable(StoreError),
+    /// The Redis instance refused the credentials of the URL.
+    AuthenticationFailed(StoreError),
+}
+
+impl ConnectError {
+    /// Returns `true` if trying again later may succeed, once the storage is up or its credentials are fixed.
+    pub fn is_temporary(&self) -> bool {
+        match *self {
+            ConnectError::InvalidUrl(_) => false,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConnectError::InvalidUrl(ref err) => write!(f, "Invalid database URL : {}", err),
+            ConnectError::Unreachable(ref err) => write!(f, "Database unreachable : {}", err),
+            ConnectError::AuthenticationFailed(ref err) => write!(f, "Database authentication failed : {}", err),
+        }
+    }
+}
+
+impl error::Error for ConnectError {
+    fn description(&self) -> &str {
+        match *self {
+            ConnectError::InvalidUrl(_) => "invalid database url",
+            ConnectError::Unreachable(_) => "database unreachable",
+            ConnectError::AuthenticationFailed(_) => "database authentication failed",
+        }
+    }
+}
+
+impl From<redis::RedisError> for ConnectError {
+    fn from(err : redis::RedisError) -> ConnectError {
+        match err.kind() {
+            redis::ErrorKind::AuthenticationFailed => ConnectError::AuthenticationFailed(StoreError::Redis(err)),
+            redis::ErrorKind::InvalidClientConfig => ConnectError::InvalidUrl(err.to_string()),
+            //Without a password in the URL, an instance requiring one accepts the connection but answers NOAUTH.
+            redis::ErrorKind::ResponseError if err.to_string().contains("NOAUTH") => {
+                ConnectError::AuthenticationFailed(StoreError::Redis(err))
+            },
+            _ => ConnectError::Unreachable(StoreError::Redis(err)),
+        }
+    }
+}
+
+/// A storage backend.
+///
+/// Batch operations have default implementations doing one operation at a time, backends able to do better should
+/// override them.
+///
+/// Stores are shared between threads, so a single `Database` can serve every thread of a process.
+pub trait ChampionStore : Send + Sync {
+    /// Loads a champion, or `None` if it was never stored.
+    ///
+    /// Stored data that can't be parsed is a `Corrupt` error.
+    fn load(&self, champion_id : u32) -> Result<Option<Champion>, StoreError>;
+
+    /// Stores a champion, replacing the previous one with the same id.
+    fn store(&self, champion : &Champion) -> Result<(), StoreError>;
+
+    /// Stores a champion only if the stored one is still `expected`, `None` meaning that it must not be stored.
+    ///
+    /// Returns `false`, writing nothing, if another writer changed the champion since `expected` was loaded.
+    ///
+    /// The default implementation loads the champion, compares it and stores it, so it doesn't protect from other
+    /// writers. Backends shared between processes or threads should override it with an atomic check.
+    fn compare_and_store(&self, expected : Option<&Champion>, champion : &Champion) -> Result<bool, StoreError> {
+        if try!(self.load(champion.get_id())).as_ref() != expected {
+            return Ok(false);
+        }
+        try!(self.store(champion));
+        Ok(true)
+    }
+
+    /// Stores the champions of `batch` that are still stored as expected, along with their nicknames, at once.
+    ///
+    /// Returns the ids of the other champions, changed by another writer since they were loaded : they aren't stored
+    /// and their nicknames aren't added.
+    ///
+    /// The default implementation compares every champion, then stores the others with `store_batch`, so it doesn't
+    /// protect from other writers. Backends shared between processes or threads should override it with an atomic
+    /// check.
+    ///
+    /// # Arguments
+    ///
+    /// * `batch` : the champions to store, each after the champion as it was loaded, `None` if it wasn't stored.
+    /// * `entries` : the nicknames to add, by champion id.
+    fn compare_and_store_batch(&self, batch : &[(Option<Champion>, Champion)], entries : &HashMap<u32, Vec<Entry>>)
+        -> Result<Vec<u32>, StoreError> {
+        let champion_ids : Vec<u32> = batch.iter().map(|&(_, ref champion)| champion.get_id()).collect();
+        let conflicts = conflicts(batch, &try!(self.load_many(&champion_ids)));
+        let champions : Vec<Champion> = batch.iter().map(|&(_, ref champion)| champion)
+            .filter(|champion| !conflicts.contains(&champion.get_id()))
+            .cloned()
+            .collect();
+        let entries = entries.iter().filter(|&(champion_id, _)| !conflicts.contains(champion_id))
+            .map(|(&champion_id, entries)| (champion_id, entries.clone()))
+            .collect();
+        try!(self.store_batch(&champions, &entries));
+        Ok(conflicts)
+    }
+
+    /// Returns the ids of every stored champion, sorted.
+    fn list(&self) -> Result<Vec<u32>, StoreError>;
+
+    /// Returns `true` if the champion is stored, even if its data is corrupt.
+    ///
+    /// The default implementation looks for it in `list`.
+    fn exists(&self, champion_id : u32) -> Result<bool, StoreError> {
+        Ok(try!(self.list()).binary_search(&champion_id).is_ok())
+    }
+
+    /// Deletes a champion and its corpus.
+    ///
+    /// Returns `true` if the champion was stored.
+    fn delete(&self, champion_id : u32) -> Result<bool, StoreError>;
+
+    /// Loads the nicknames a champion was fed with. The corpus is empty if the champion has none.
+    fn load_corpus(&self, champion_id : u32) -> Result<Corpus, StoreError>;
+
+    /// Adds nicknames to the corpus of a champion. Nicknames already in it keep their entry.
+    fn add_to_corpus(&self, champion_id : u32, entries : &[Entry]) -> Result<(), StoreError>;
+
+    /// Loads the champion registry, or `None` if it was never stored. A stored registry that can't be parsed is a
+    /// `Corrupt` error.
+    fn load_registry(&self) -> Result<Option<ChampionRegistry>, StoreError>;
+
+    /// Stores the champion registry, replacing the previous one.
+    fn store_registry(&self, registry : &ChampionRegistry) -> Result<(), StoreError>;
+
+    /// Feeds nicknames to a champion and adds them to its corpus, creating the champion if needed.
+    ///
+    /// Returns the number of nicknames the champion learnt something from, see `Champion::feed`.
+    ///
+    /// The default implementation loads the champion, feeds it and stores it back. Backends shared between processes
+    /// should override it with an atomic update.
+    fn feed(&self, champion_id : u32, entries : &[Entry]) -> Result<usize, StoreError> {
+        let mut champion = try!(self.load(champion_id)).unwrap_or_else(|| Champion::new(champion_id));
+        let fed = champion.feed_all(entries.iter().map(|entry| entry.nickname.as_str()));
+        let mut batch = HashMap::new();
+        batch.insert(champion_id, entries.to_vec());
+        try!(self.store_batch(&[champion], &batch));
+        Ok(fed)
+    }
+
+    /// Moves data written by older versions to the layout of this one.
+    ///
+    /// Returns the number of items moved. The default implementation has nothing to move.
+    fn migrate(&self) -> Result<usize, StoreError> {
+        Ok(0)
+    }
+
+    /// Loads many champions, in the order of `champion_ids`.
+    fn load_many(&self, champion_ids : &[u32]) -> Result<Vec<Option<Champion>>, StoreError> {
+        champion_ids.iter().map(|&champion_id| self.load(champion_id)).collect()
+    }
+
+    /// Stores many champions.
+    fn store_many(&self, champions : &[Champion]) -> Result<(), StoreError> {
+        for champion in champions {
+            try!(self.store(champion));
+        }
+        Ok(())
+    }
+
+    /// Stores champions and adds nicknames to their corpus, see `Database::store_batch`.
+    ///
+    /// # Arguments
+    ///
+    /// * `champions` : the champions to store.
+    /// * `entries` : the nicknames to add, by champion id.
+    fn store_batch(&self, champions : &[Champion], entries : &HashMap<u32, Vec<Entry>>) -> Result<(), StoreError> {
+        try!(self.store_many(champions));
+        for (&champion_id, entries) in entries {
+            try!(self.add_to_corpus(champion_id, entries));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+//! A pool of Redis connections, shared by every thread using a `RedisStore`.
+//!
+//! Idle connections are checked with a `PING` before being handed out, and dropped if they don't answer, so that a
+//! restarted Redis instance is reconnected to instead of failing every request until the process is restarted. Opening
+//! a connection is tried again with an exponential backoff.
+extern crate redis;
+use std::cmp;
+use std::ops::Deref;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+/// How a `RedisPool` keeps and opens connections.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolConfig {
+    /// The number of idle connections kept open. More are opened when every connection is in use.
+    pub max_idle : usize,
+    /// How many times opening a connection, or sending a request on a broken one, is tried again.
+    pub retries : u32,
+    /// The wait before the first retry, doubled at each following one.
+    pub backoff : Duration,
+    /// The longest wait between two retries.
+    pub max_backoff : Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> PoolConfig {
+        PoolConfig {
+            max_idle : 8,
+            retries : 5,
+            backoff : Duration::from_millis(100),
+            max_backoff : Duration::from_secs(5),
+        }
+    }
+}
+
+impl PoolConfig {
+    /// Returns how long to wait before a retry.
+    ///
+    /// # Arguments
+    ///
+    /// * `attempt` : the number of retries already done.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::store::redis_pool::*;
+    /// use std::time::Duration;
+    ///
+    /// let config = PoolConfig::default();
+    /// assert_eq!(Duration::from_millis(400), config.delay(2));
+    /// assert_eq!(Duration::from_secs(5), config.delay(20));
+    /// ```
+    pub fn delay(&self, attempt : u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt).unwrap_or(u32::max_value());
+        cmp::min(self.backoff.checked_mul(factor).unwrap_or(self.max_backoff), self.max_backoff)
+    }
+}
+
+/// Connections to a Redis instance.
+pub struct RedisPool {
+    client : redis::Client,
+    config : PoolConfig,
+    idle : Mutex<Vec<redis::Connection>>,
+}
+
+impl RedisPool {
+    /// Creates a pool, opening a first connection so that an unreachable instance, or wrong credentials, are noticed
+    /// right away.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` : the client opening connections.
+    /// * `config` : how connections are kept and opened.
+    pub fn new(client : redis::Client, config : PoolConfig) -> redis::RedisResult<RedisPool> {
+        let pool = RedisPool {
+            client : client,
+            config : config,
+            idle : Mutex::new(Vec::new()),
+        };
+        {
+            //An instance requiring a password may accept the connection and only refuse requests.
+            let con = try!(pool.get());
+            try!(redis::cmd("PING").query::<String>(&*con));
+        }
+        Ok(pool)
+    }
+
+    /// Returns how connections are kept and opened.
+    pub fn get_config(&self) -> &PoolConfig {
+        &self.config
+    }
+
+    /// Hands out a connection answering to `PING`, opening a new one if no idle connection does.
+    ///
+    /// The connection goes back to the pool when dropped.
+    ///
+    /// # Errors
+    ///
+    /// If no connection could be opened after `retries` retries, or if the instance refused it.
+    pub fn get(&self) -> redis::RedisResult<PooledConnection> {
+        //Idle connections may have been closed by a restart of the instance.
+        loop {
+            let con = self.lock().pop();
+            match con {
+                Some(con) => if RedisPool::ping(&con) {
+                    return Ok(PooledConnection { pool : self, con : Some(con) });
+                },
+                None => break,
+            }
+        }
+
+        let mut attempt = 0;
+        loop {
+            match self.client.get_connection() {
+                Ok(con) => return Ok(PooledConnection { pool : self, con : Some(con) }),
+                Err(err) => if attempt >= self.config.retries || !RedisPool::is_connection_error(&err) {
+                    return Err(err);
+                },
+            }
+            thread::sleep(self.config.delay(attempt));
+            attempt += 1;
+        }
+    }
+
+    /// Returns `true` if the error means the connection is broken, rather than the request being wrong.
+    pub fn is_connection_error(err : &redis::RedisError) -> bool {
+        err.kind() == redis::ErrorKind::IoError
+    }
+
+    fn ping(con : &redis::Connection) -> bool {
+        redis::cmd("PING").query::<String>(con).is_ok()
+    }
+
+    fn lock(&self) -> MutexGuard<Vec<redis::Connection>> {
+        //Connections are pushed and popped whole, a panicking thread can't leave the list inconsistent.
+        match self.idle.lock() {
+            Ok(idle) => idle,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// A connection borrowed from a `RedisPool`, given back when dropped.
+pub struct PooledConnection<'a> {
+    pool : &'a RedisPool,
+    con : Option<redis::Connection>,
+}
+
+impl<'a> PooledConnection<'a> {
+    /// Closes the connection instead of giving it back, when it is known to be broken.
+    pub fn discard(mut self) {
+        self.con = None;
+    }
+}
+
+impl<'a> Deref for PooledConnection<'a> {
+    type Target = redis::Connection;
+
+    fn deref(&self) -> &redis::Connection {
+        self.con.as_ref().unwrap()
+    }
+}
+
+impl<'a> Drop for PooledConnection<'a> {
+    fn drop(&mut self) {
+        if let Some(con) = self.con.take() {
+            let mut idle = self.pool.lock();
+            if idle.len() < self.pool.config.max_idle {
+                idle.push(con);
+            }
+        }
+    }
+}
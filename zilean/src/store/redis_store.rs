@@ -0,0 +1,628 @@
+//! Stores champions in a Redis instance.
+//!
+//! Keys live in a namespace, `zelkow:v1` unless the URL sets another one (`redis://127.0.0.1/?namespace=staging`), so
+//! that the instance can be shared with other applications.
+//!
+//! Requests go through a `RedisPool`, so that the store can be used by many threads at once and survives restarts of
+//! the instance. See `RedisConfig` for the URL parameters tuning it.
+//!
+//! Each champion is a hash under `<namespace>:champion:<id>` : the `order` field holds the chain order, and each
+//! transition is a `t:<state><letter>` field holding how many times `letter` followed `state`. Feeding a nickname
+//! increments these fields with `HINCRBY` on the server, so that writers never overwrite each other's updates.
+//! Champions replaced as a whole by `Database::update` are compared under `WATCH`, so that what was fed in the meantime
+//! is never overwritten.
+//!
+//! Champions stored by older versions, as a JSON string, are still loaded, and are converted to a hash
+//! the first time they are stored or fed.
+//!
+//! The corpus of a champion is a hash under `<namespace>:corpus:<id>` and the registry is stored under
+//! `<namespace>:registry`. Older versions stored them without namespace, under `<id>`, `corpus:<id>` and `registry` :
+//! `Database::migrate` moves them to the namespace. Only keys holding a legacy champion, corpus or registry are moved, so
+//! that keys of other applications are left alone.
+extern crate redis;
+use ::champion::Champion as Champion;
+use ::corpus::{Corpus, Entry};
+use ::registry::ChampionRegistry as ChampionRegistry;
+use ::store::{ChampionStore, ConnectError, StoreError};
+use ::store::redis_pool::{PoolConfig, RedisPool};
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+use self::redis::Commands;
+
+/// The namespace used when the URL doesn't set one.
+pub const DEFAULT_NAMESPACE : &'static str = "zelkow:v1";
+
+//The keys used by versions without namespace.
+const LEGACY_CORPUS_PREFIX : &'static str = "corpus:";
+const LEGACY_REGISTRY_KEY : &'static str = "registry";
+
+const ORDER_FIELD : &'static str = "order";
+//Keeps transition fields apart from ORDER_FIELD, which an order 4 transition could otherwise be.
+const TRANSITION_PREFIX : &'static str = "t:";
+
+//Feeds nicknames to a champion hash, see RedisStore::feed.
+//KEYS : the champion hash, the corpus hash.
+//ARGV : the chain order, then for each nickname : the nickname, its corpus entry, its number of transitions and the
+//transition fields.
+//Like Champion::feed, a nickname is only counted if one of its transitions is unknown.
+const FEED_SCRIPT : &'static str = r"
+    local order = redis.call('HGET', KEYS[1], 'order')
+    if order and order ~= ARGV[1] then
+        return redis.error_reply('chain order mismatch')
+    end
+    redis.call('HSETNX', KEYS[1], 'order', ARGV[1])
+    local fed = 0
+    local i = 2
+    while i <= #ARGV do
+        local count = tonumber(ARGV[i + 2])
+        redis.call('HSETNX', KEYS[2], ARGV[i], ARGV[i + 1])
+        local known = true
+        for j = i + 3, i + 2 + count do
+            if redis.call('HEXISTS', KEYS[1], ARGV[j]) == 0 then
+                known = false
+                break
+            end
+        end
+        if not known then
+            for j = i + 3, i + 2 + count do
+                redis.call('HINCRBY', KEYS[1], ARGV[j], 1)
+            end
+            fed = fed + 1
+        end
+        i = i + 3 + count
+    end
+    return fed
+";
+
+/// How a `RedisStore` names its keys and handles its connections.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RedisConfig {
+    /// The namespace of the keys. An empty namespace means no namespace.
+    pub namespace : String,
+    /// How connections are kept and opened.
+    pub pool : PoolConfig,
+}
+
+impl Default for RedisConfig {
+    fn default() -> RedisConfig {
+        RedisConfig {
+            namespace : DEFAULT_NAMESPACE.to_string(),
+            pool : PoolConfig::default(),
+        }
+    }
+}
+
+impl RedisConfig {
+    /// Takes the parameters of the store out of a URL, returning the URL left for the redis client and the
+    /// configuration. Missing parameters keep their default value.
+    ///
+    /// * `namespace` : the namespace of the keys.
+    /// * `pool_size` : the number of idle connections kept open.
+    /// * `retries` : how many times opening a connection, or sending a request on a broken one, is tried again.
+    /// * `backoff_ms` : the wait before the first retry, in milliseconds, doubled at each following one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use self::zilean::store::redis_store::*;
+    ///
+    /// let (url, config) = RedisConfig::from_url("redis://127.0.0.1/?namespace=staging&retries=3").unwrap();
+    /// assert_eq!("redis://127.0.0.1/", url);
+    /// assert_eq!("staging", config.namespace);
+    /// assert_eq!(3, config.pool.retries);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If a numeric parameter isn't a number.
+    pub fn from_url(url : &str) -> Result<(String, RedisConfig), String> {
+        let (base, query) = match url.find('?') {
+            Some(i) => (&url[..i], &url[i + 1..]),
+            None => (url, ""),
+        };
+        let mut config = RedisConfig::default();
+        let mut params = Vec::new();
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (name, value) = match param.find('=') {
+                Some(i) => (&param[..i], &param[i + 1..]),
+                None => (param, ""),
+            };
+            let invalid = |err : ::std::num::ParseIntError| format!("Invalid {} parameter {:?} : {}", name, value, err);
+            match name {
+                "namespace" => config.namespace = value.to_string(),
+                "pool_size" => config.pool.max_idle = try!(value.parse().map_err(invalid)),
+                "retries" => config.pool.retries = try!(value.parse().map_err(invalid)),
+                "backoff_ms" => config.pool.backoff = Duration::from_millis(try!(value.parse().map_err(invalid))),
+                _ => params.push(param),
+            }
+        }
+        if params.is_empty() {
+            Ok((base.to_string(), config))
+        } else {
+            Ok((format!("{}?{}", base, params.join("&")), config))
+        }
+    }
+}
+
+/// Connections to a Redis instance.
+pub struct RedisStore {
+    pool : RedisPool,
+    //Ends with a colon, unless it is empty.
+    prefix : String,
+}
+
+impl RedisStore {
+    /// Connects to a redis instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` : the URL of the instance, like `redis://127.0.0.1`, with the parameters described by
+    /// `RedisConfig::from_url`, like `redis://127.0.0.1/?namespace=staging`.
+    ///
+    /// # Errors
+    ///
+    /// If the URL is malformed, if the instance can't be reached or if it refuses the credentials of the URL.
+    pub fn connect(url : &str) -> Result<RedisStore, ConnectError> {
+        let (url, config) = try!(RedisConfig::from_url(url).map_err(ConnectError::InvalidUrl));
+        RedisStore::connect_with(&url, config)
+    }
+
+    /// Connects to a redis instance with the given configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` : the URL of the instance, like `redis://127.0.0.1`.
+    /// * `config` : how keys are named and connections handled.
+    ///
+    /// # Errors
+    ///
+    /// If the URL is malformed, if the instance can't be reached or if it refuses the credentials of the URL.
+    pub fn connect_with(url : &str, config : RedisConfig) -> Result<RedisStore, ConnectError> {
+        let redis_url = try!(redis::parse_redis_url(url)
+            .map_err(|_| ConnectError::InvalidUrl(format!("Malformed Redis URL {:?}", url))));
+        let client = try!(redis::Client::open(redis_url));
+        let namespace = config.namespace;
+        Ok(RedisStore {
+            pool : try!(RedisPool::new(client, config.pool)),
+            prefix : if namespace.is_empty() { namespace } else { format!("{}:", namespace) },
+        })
+    }
+
+    //Runs `f` on a pooled connection. If the connection broke, `f` runs again on another one, so it must be safe to
+    //repeat.
+    fn run<T, F>(&self, mut f : F) -> Result<T, StoreError>
+        where F : FnMut(&redis::Connection) -> Result<T, StoreError> {
+        let mut attempt = 0;
+        loop {
+            let con = try!(self.pool.get());
+            match f(&con) {
+                Err(StoreError::Redis(ref err))
+                    if RedisPool::is_connection_error(err) && attempt < self.pool.get_config().retries => con.discard(),
+                result => return result,
+            }
+            thread::sleep(self.pool.get_config().delay(attempt));
+            attempt += 1;
+        }
+    }
+
+    fn champion_key(&self, champion_id : u32) -> String {
+        format!("{}champion:{}", self.prefix, champion_id)
+    }
+
+    fn corpus_key(&self, champion_id : u32) -> String {
+        format!("{}corpus:{}", self.prefix, champion_id)
+    }
+
+    fn registry_key(&self) -> String {
+        format!("{}registry", self.prefix)
+    }
+
+    //Returns where a key written by a version without namespace belongs, or None if its name isn't one.
+    fn migrated_key(&self, key : &str) -> Option<String> {
+        if let Ok(champion_id) = key.parse() {
+            Some(self.champion_key(champion_id))
+        } else if key.starts_with(LEGACY_CORPUS_PREFIX) {
+            key[LEGACY_CORPUS_PREFIX.len()..].parse().ok().map(|champion_id| self.corpus_key(champion_id))
+        } else if key == LEGACY_REGISTRY_KEY {
+            Some(self.registry_key())
+        } else {
+            None
+        }
+    }
+
+    //Whether a key named like a legacy one holds what older versions wrote there : a champion as a JSON string, a
+    //corpus as a hash of entries, or the registry as static data.
+    fn is_legacy(con : &redis::Connection, key : &str) -> Result<bool, StoreError> {
+        let key_type : String = try!(redis::cmd("TYPE").arg(key).query(con));
+        if let Ok(champion_id) = key.parse() {
+            if key_type != "string" {
+                return Ok(false);
+            }
+            let val : String = try!(con.get(key));
+            Ok(RedisStore::parse_legacy(champion_id, &val).is_ok())
+        } else if key.starts_with(LEGACY_CORPUS_PREFIX) {
+            if key_type != "hash" {
+                return Ok(false);
+            }
+            let entries : HashMap<String, String> = try!(con.hgetall(key));
+            Ok(entries.into_iter().all(|(nickname, val)| Entry::deserialize(nickname, &val).is_ok()))
+        } else {
+            if key_type != "string" {
+                return Ok(false);
+            }
+            let val : String = try!(con.get(key));
+            Ok(ChampionRegistry::from_static_data(&val).is_ok())
+        }
+    }
+
+    fn transition_field(state : &str, letter : char) -> String {
+        format!("{}{}{}", TRANSITION_PREFIX, state, letter)
+    }
+
+    //Champions stored by older versions are a JSON string.
+    fn parse_legacy(champion_id : u32, val : &str) -> Result<Champion, StoreError> {
+        let mut champion = Champion::new(champion_id);
+        try!(champion.try_deserialize(val).map_err(|err| ::store::corrupt(champion_id, err)));
+        Ok(champion)
+    }
+
+    //Champions stored by older versions have no order field, and an order 1 chain.
+    fn parse_order(champion_id : u32, order : Option<&String>) -> Result<usize, StoreError> {
+        let order = match order {
+            Some(order) => try!(order.parse().map_err(|err| ::store::corrupt(champion_id, err))),
+            None => 1,
+        };
+        if order < 1 {
+            return Err(::store::corrupt(champion_id, "a chain order must be at least 1"));
+        }
+        Ok(order)
+    }
+
+    //Fields are read as strings, so that a field which isn't a number is reported as corrupt data.
+    fn parse_hash(champion_id : u32, fields : HashMap<String, String>) -> Result<Champion, StoreError> {
+        let order = try!(RedisStore::parse_order(champion_id, fields.get(ORDER_FIELD)));
+        let mut champion = Champion::with_order(champion_id, order);
+        for (field, count) in fields {
+            if field.starts_with(TRANSITION_PREFIX) {
+                let count = try!(count.parse().map_err(|err| ::store::corrupt(champion_id, err)));
+                let mut state = field[TRANSITION_PREFIX.len()..].to_string();
+                if let Some(letter) = state.pop() {
+                    champion.add_transition(&state, letter, count);
+                }
+            }
+        }
+        Ok(champion)
+    }
+
+    //Loads a champion from the value of its key, of type `key_type` as returned by TYPE.
+    fn parse_typed(champion_id : u32, key_type : &str, value : &redis::Value) -> Result<Option<Champion>, StoreError> {
+        match key_type {
+            "hash" => Ok(Some(try!(RedisStore::parse_hash(champion_id, try!(redis::from_redis_value(value)))))),
+            "string" => {
+                let val : String = try!(redis::from_redis_value(value));
+                Ok(Some(try!(RedisStore::parse_legacy(champion_id, &val))))
+            },
+            "none" => Ok(None),
+            _ => Err(::store::corrupt(champion_id, format!("unexpected {} key", key_type))),
+        }
+    }
+
+    //Loads a champion stored with the type `key_type`, as returned by TYPE.
+    fn load_typed(&self, con : &redis::Connection, champion_id : u32, key_type : &str)
+        -> Result<Option<Champion>, StoreError> {
+        let key = self.champion_key(champion_id);
+        let value : redis::Value = match key_type {
+            "hash" => try!(con.hgetall(&key)),
+            "string" => try!(con.get(&key)),
+            _ => redis::Value::Nil,
+        };
+        RedisStore::parse_typed(champion_id, key_type, &value)
+    }
+
+    //Loads many champions, in the order of `champion_ids`.
+    fn read_many(&self, con : &redis::Connection, champion_ids : &[u32]) -> Result<Vec<Option<Champion>>, StoreError> {
+        //Legacy and hash champions are read differently, so we ask for the types first.
+        let mut pipe = redis::pipe();
+        for &champion_id in champion_ids {
+            pipe.cmd("TYPE").arg(self.champion_key(champion_id));
+        }
+        let key_types : Vec<String> = try!(pipe.query(con));
+
+        let mut pipe = redis::pipe();
+        for (&champion_id, key_type) in champion_ids.iter().zip(&key_types) {
+            match key_type.as_str() {
+                "hash" => pipe.hgetall(self.champion_key(champion_id)),
+                "string" => pipe.get(self.champion_key(champion_id)),
+                _ => pipe.cmd("PING"),
+            };
+        }
+        let values : Vec<redis::Value> = try!(pipe.query(con));
+
+        let mut champions = Vec::new();
+        for ((&champion_id, key_type), value) in champion_ids.iter().zip(&key_types).zip(values) {
+            champions.push(try!(RedisStore::parse_typed(champion_id, key_type, &value)));
+        }
+        Ok(champions)
+    }
+
+    //Runs `f` in a transaction watching `keys`, like `redis::transaction`. If `f` fails, the keys are unwatched, so
+    //that the connection doesn't go back to the pool still watching them.
+    fn transaction<T, F>(con : &redis::Connection, keys : &[&str], f : F) -> redis::RedisResult<T>
+        where F : FnMut(&mut redis::Pipeline) -> redis::RedisResult<Option<T>> {
+        let res = redis::transaction(con, keys, f);
+        if res.is_err() {
+            let _ : redis::RedisResult<()> = redis::cmd("UNWATCH").query(con);
+        }
+        res
+    }
+
+    fn key_type(&self, con : &redis::Connection, champion_id : u32) -> Result<String, StoreError> {
+        Ok(try!(redis::cmd("TYPE").arg(self.champion_key(champion_id)).query(con)))
+    }
+
+    //Queues the writes replacing a champion by its hash.
+    fn pipe_champion(&self, pipe : &mut redis::Pipeline, champion : &Champion) {
+        let key = self.champion_key(champion.get_id());
+        pipe.del(&key).ignore();
+        pipe.hset(&key, ORDER_FIELD, champion.get_order()).ignore();
+        for (state, letter, count) in champion.transitions() {
+            pipe.hset(&key, RedisStore::transition_field(&state, letter), count).ignore();
+        }
+    }
+
+    //Queues the writes adding entries to a corpus.
+    fn pipe_corpus(&self, pipe : &mut redis::Pipeline, champion_id : u32, entries : &[Entry])
+        -> Result<(), StoreError> {
+        let key = self.corpus_key(champion_id);
+        for entry in entries {
+            pipe.hset_nx(&key, &entry.nickname, try!(entry.serialize())).ignore();
+        }
+        Ok(())
+    }
+}
+
+impl ChampionStore for RedisStore {
+    fn load(&self, champion_id : u32) -> Result<Option<Champion>, StoreError> {
+        self.run(|con| {
+            let key_type = try!(self.key_type(con, champion_id));
+            self.load_typed(con, champion_id, &key_type)
+        })
+    }
+
+    fn store(&self, champion : &Champion) -> Result<(), StoreError> {
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        self.pipe_champion(&mut pipe, champion);
+        self.run(|con| Ok(try!(pipe.query(con))))
+    }
+
+    //The key is watched while the stored champion is compared, so that the transaction fails if another writer
+    //changed it in between. It is then compared again, and only written if it still is `expected`.
+    fn compare_and_store(&self, expected : Option<&Champion>, champion : &Champion) -> Result<bool, StoreError> {
+        let champion_id = champion.get_id();
+        let key = self.champion_key(champion_id);
+        //Corrupt data can't be reported by the transaction itself, it is returned as its result.
+        let stored : Result<bool, StoreError> = try!(self.run(|con| {
+            Ok(try!(RedisStore::transaction(con, &[&key], |pipe| {
+                let current = match self.key_type(con, champion_id)
+                    .and_then(|key_type| self.load_typed(con, champion_id, &key_type)) {
+                    Ok(current) => current,
+                    Err(StoreError::Redis(err)) => return Err(err),
+                    Err(err) => return Ok(Some(Err(err))),
+                };
+                if current.as_ref() != expected {
+                    return Ok(Some(Ok(false)));
+                }
+                self.pipe_champion(pipe, champion);
+                let executed : Option<()> = try!(pipe.query(con));
+                Ok(executed.map(|_| Ok(true)))
+            })))
+        }));
+        stored
+    }
+
+    //Every key is watched while the stored champions are compared, so that the transaction fails if another writer
+    //changed one of them in between. They are then compared again, and the changed ones left out.
+    fn compare_and_store_batch(&self, batch : &[(Option<Champion>, Champion)], entries : &HashMap<u32, Vec<Entry>>)
+        -> Result<Vec<u32>, StoreError> {
+        if batch.is_empty() {
+            return Ok(Vec::new());
+        }
+        let champion_ids : Vec<u32> = batch.iter().map(|&(_, ref champion)| champion.get_id()).collect();
+        let keys : Vec<String> = champion_ids.iter().map(|&champion_id| self.champion_key(champion_id)).collect();
+        let keys : Vec<&str> = keys.iter().map(|key| key.as_str()).collect();
+        //Corrupt data can't be reported by the transaction itself, it is returned as its result.
+        let conflicts : Result<Vec<u32>, StoreError> = try!(self.run(|con| {
+            Ok(try!(RedisStore::transaction(con, &keys, |pipe| {
+                let current = match self.read_many(con, &champion_ids) {
+                    Ok(current) => current,
+                    Err(StoreError::Redis(err)) => return Err(err),
+                    Err(err) => return Ok(Some(Err(err))),
+                };
+                let conflicts = ::store::conflicts(batch, &current);
+                if conflicts.len() == batch.len() {
+                    return Ok(Some(Ok(conflicts)));
+                }
+                for &(_, ref champion) in batch {
+                    if !conflicts.contains(&champion.get_id()) {
+                        self.pipe_champion(pipe, champion);
+                    }
+                }
+                for (&champion_id, entries) in entries {
+                    if conflicts.contains(&champion_id) {
+                        continue;
+                    }
+                    if let Err(err) = self.pipe_corpus(pipe, champion_id, entries) {
+                        return Ok(Some(Err(err)));
+                    }
+                }
+                let executed : Option<()> = try!(pipe.query(con));
+                Ok(executed.map(|_| Ok(conflicts)))
+            })))
+        }));
+        conflicts
+    }
+
+    fn list(&self) -> Result<Vec<u32>, StoreError> {
+        let prefix = format!("{}champion:", self.prefix);
+        //The namespace could hold glob characters.
+        let mut pattern = String::new();
+        for c in prefix.chars() {
+            if "*?[]\\".contains(c) {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push('*');
+        let mut champion_ids = try!(self.run(|con| {
+            let keys : redis::Iter<String> = try!(con.scan_match(&pattern));
+            Ok(keys.filter_map(|key| key[prefix.len()..].parse().ok()).collect::<Vec<u32>>())
+        }));
+        champion_ids.sort();
+        champion_ids.dedup();
+        Ok(champion_ids)
+    }
+
+    fn exists(&self, champion_id : u32) -> Result<bool, StoreError> {
+        let key = self.champion_key(champion_id);
+        self.run(|con| Ok(try!(con.exists(&key))))
+    }
+
+    fn delete(&self, champion_id : u32) -> Result<bool, StoreError> {
+        let mut pipe = redis::pipe();
+        pipe.del(self.champion_key(champion_id)).del(self.corpus_key(champion_id));
+        let (deleted, _) : (u32, u32) = try!(self.run(|con| Ok(try!(pipe.query(con)))));
+        Ok(deleted > 0)
+    }
+
+    fn load_corpus(&self, champion_id : u32) -> Result<Corpus, StoreError> {
+        let mut corpus = Corpus::new(champion_id);
+        let key = self.corpus_key(champion_id);
+        let entries : HashMap<String, String> = try!(self.run(|con| Ok(try!(con.hgetall(&key)))));
+        for (nickname, val) in entries {
+            if let Ok(entry) = Entry::deserialize(nickname, &val) {
+                corpus.add(entry);
+            }
+        }
+        Ok(corpus)
+    }
+
+    fn add_to_corpus(&self, champion_id : u32, entries : &[Entry]) -> Result<(), StoreError> {
+        let mut pipe = redis::pipe();
+        try!(self.pipe_corpus(&mut pipe, champion_id, entries));
+        self.run(|con| Ok(try!(pipe.query(con))))
+    }
+
+    //Feeds nicknames with a server side script, incrementing the transitions of the nicknames that bring something new.
+    //Concurrent feeds can't lose each other's updates, and the champion isn't loaded.
+    fn feed(&self, champion_id : u32, entries : &[Entry]) -> Result<usize, StoreError> {
+        let key = self.champion_key(champion_id);
+        let order : Option<String> = try!(self.run(|con| {
+            //A champion stored by an older version is converted first. The key is watched, so that the conversion
+            //can't overwrite what another writer fed in the meantime.
+            if try!(self.key_type(con, champion_id)) == "string" {
+                //Corrupt data can't be reported by the transaction itself, it is returned as its result.
+                let converted : Result<(), StoreError> = try!(RedisStore::transaction(con, &[&key], |pipe| {
+                    let key_type : String = try!(redis::cmd("TYPE").arg(&key).query(con));
+                    if key_type != "string" {
+                        return Ok(Some(Ok(())));
+                    }
+                    let val : String = try!(con.get(&key));
+                    match RedisStore::parse_legacy(champion_id, &val) {
+                        Ok(champion) => self.pipe_champion(pipe, &champion),
+                        Err(err) => return Ok(Some(Err(err))),
+                    }
+                    let executed : Option<()> = try!(pipe.query(con));
+                    Ok(executed.map(Ok))
+                }));
+                try!(converted);
+            }
+            Ok(try!(con.hget(&key, ORDER_FIELD)))
+        }));
+        let order = try!(RedisStore::parse_order(champion_id, order.as_ref()));
+        //The transitions only depend on the order.
+        let champion = Champion::with_order(champion_id, order);
+
+        let script = redis::Script::new(FEED_SCRIPT);
+        let mut invocation = script.key(&key);
+        invocation.key(self.corpus_key(champion_id)).arg(champion.get_order());
+        for entry in entries {
+            let transitions = champion.nickname_transitions(&entry.nickname);
+            invocation.arg(&entry.nickname).arg(try!(entry.serialize())).arg(transitions.len());
+            for (state, letter) in transitions {
+                invocation.arg(RedisStore::transition_field(&state, letter));
+            }
+        }
+        //The script isn't run again if the connection breaks : it may have run already, and would count the nicknames
+        //twice.
+        let con = try!(self.pool.get());
+        Ok(try!(invocation.invoke(&*con)))
+    }
+
+    fn load_registry(&self) -> Result<Option<ChampionRegistry>, StoreError> {
+        let val : Option<String> = try!(self.run(|con| Ok(try!(con.get(self.registry_key())))));
+        match val {
+            Some(val) => Ok(Some(try!(::store::parse_registry(&val)))),
+            None => Ok(None),
+        }
+    }
+
+    fn store_registry(&self, registry : &ChampionRegistry) -> Result<(), StoreError> {
+        let val = try!(registry.serialize());
+        self.run(|con| Ok(try!(con.set(self.registry_key(), &val))))
+    }
+
+    //Keys already present in the namespace are never overwritten, the old key is left in place instead. Keys which don't
+    //hold legacy data are left in place too, they belong to other applications.
+    fn migrate(&self) -> Result<usize, StoreError> {
+        //Keys are collected first, renaming them while scanning could return them twice.
+        let mut keys = try!(self.run(|con| {
+            let keys : redis::Iter<String> = try!(con.scan());
+            Ok(keys.collect::<Vec<String>>())
+        }));
+        keys.sort();
+        keys.dedup();
+        let mut moved = 0;
+        for key in keys {
+            if let Some(target) = self.migrated_key(&key) {
+                if target != key {
+                    let renamed : bool = try!(self.run(|con| {
+                        if !try!(RedisStore::is_legacy(con, &key)) {
+                            return Ok(false);
+                        }
+                        Ok(try!(con.rename_nx(&key, &target)))
+                    }));
+                    if renamed {
+                        moved += 1;
+                    }
+                }
+            }
+        }
+        Ok(moved)
+    }
+
+    fn load_many(&self, champion_ids : &[u32]) -> Result<Vec<Option<Champion>>, StoreError> {
+        if champion_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.run(|con| self.read_many(con, champion_ids))
+    }
+
+    fn store_many(&self, champions : &[Champion]) -> Result<(), StoreError> {
+        self.store_batch(champions, &HashMap::new())
+    }
+
+    fn store_batch(&self, champions : &[Champion], entries : &HashMap<u32, Vec<Entry>>) -> Result<(), StoreError> {
+        //Everything is written in a single transaction.
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for champion in champions {
+            self.pipe_champion(&mut pipe, champion);
+        }
+        for (&champion_id, entries) in entries {
+            try!(self.pipe_corpus(&mut pipe, champion_id, entries));
+        }
+        self.run(|con| Ok(try!(pipe.query(con))))
+    }
+}
@@ -0,0 +1,315 @@
+//! Stores champions in an SQLite database, in tables that can be queried with plain SQL.
+//!
+//! * `champion (id, chain_order)` : one row per stored champion.
+//! * `transition (champion_id, state, letter, count)` : how many times `letter` followed `state`. The states a nickname
+//!   starts from are stored without the terminating character, so the first letters are the transitions of the empty
+//!   state, and an empty `letter` ends a nickname. The `start` view only holds the first letters.
+//! * `nickname (champion_id, nickname, first_seen, realm, game_id)` : the corpus.
+//! * `registry (id, data)` : the champion registry, as static data.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use self::zilean::database::*;
+//!
+//! let db = Database::new("sqlite:///var/lib/zelkow/zelkow.sqlite".to_string());
+//! let champion = db.load(10).unwrap();
+//! ```
+//!
+//! ```sql
+//! -- The most common first letters of every champion.
+//! SELECT champion_id, letter, count FROM start ORDER BY champion_id, count DESC;
+//! ```
+extern crate rusqlite;
+use ::champion::{Champion, TERMINATOR};
+use ::corpus::{Corpus, Entry};
+use ::registry::ChampionRegistry as ChampionRegistry;
+use ::store::{ChampionStore, StoreError};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use self::rusqlite::Connection;
+
+//How long a connection waits for another process to release the database before failing, in milliseconds.
+const BUSY_TIMEOUT : u32 = 5000;
+
+const SCHEMA : &'static str = "
+    CREATE TABLE IF NOT EXISTS champion (
+        id INTEGER PRIMARY KEY,
+        chain_order INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS transition (
+        champion_id INTEGER NOT NULL,
+        state TEXT NOT NULL,
+        letter TEXT NOT NULL,
+        count INTEGER NOT NULL,
+        PRIMARY KEY (champion_id, state, letter)
+    );
+    CREATE TABLE IF NOT EXISTS nickname (
+        champion_id INTEGER NOT NULL,
+        nickname TEXT NOT NULL,
+        first_seen INTEGER NOT NULL,
+        realm TEXT,
+        game_id INTEGER,
+        PRIMARY KEY (champion_id, nickname)
+    );
+    CREATE TABLE IF NOT EXISTS registry (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        data TEXT NOT NULL
+    );
+    CREATE VIEW IF NOT EXISTS start AS
+        SELECT champion_id, letter, count FROM transition WHERE state = '';
+";
+
+/// A connection to an SQLite database.
+pub struct SqliteStore {
+    //SQLite connections can't be shared between threads.
+    con : Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens a database file, creating it and its tables if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` : the database file, like `/var/lib/zelkow/zelkow.sqlite`.
+    pub fn open<P>(path : P) -> Result<SqliteStore, rusqlite::Error> where P : AsRef<Path> {
+        let con = try!(Connection::open(path));
+        try!(con.execute_batch(&format!("PRAGMA busy_timeout = {};", BUSY_TIMEOUT)));
+        try!(con.execute_batch(SCHEMA));
+        Ok(SqliteStore {
+            con : Mutex::new(con),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<Connection> {
+        match self.con.lock() {
+            Ok(con) => con,
+            Err(poisoned) => {
+                //The panicking thread may have left a transaction open.
+                let con = poisoned.into_inner();
+                let _ = con.execute_batch("ROLLBACK");
+                con
+            },
+        }
+    }
+
+    //Runs `f` in a transaction, rolled back if `f` fails.
+    fn transaction<F>(&self, f : F) -> Result<(), StoreError> where F : FnOnce(&Connection) -> Result<(), StoreError> {
+        let con = self.lock();
+        //IMMEDIATE takes the write lock right away, so that another process can't write between what `f` reads and
+        //what it writes. If another process holds it, we wait up to the busy timeout.
+        try!(con.execute_batch("BEGIN IMMEDIATE"));
+        match f(&con) {
+            Ok(()) => Ok(try!(con.execute_batch("COMMIT"))),
+            Err(err) => {
+                let _ = con.execute_batch("ROLLBACK");
+                Err(err)
+            },
+        }
+    }
+
+    fn read_champion(con : &Connection, champion_id : u32) -> Result<Option<Champion>, StoreError> {
+        let mut select = try!(con.prepare("SELECT chain_order FROM champion WHERE id = ?"));
+        let order : i64 = match try!(select.query_map(&[&(champion_id as i64)], |row| row.get(0))).next() {
+            Some(order) => try!(order),
+            None => return Ok(None),
+        };
+
+        if order < 1 {
+            return Err(::store::corrupt(champion_id, format!("invalid chain order {}", order)));
+        }
+        let mut champion = Champion::with_order(champion_id, order as usize);
+        let mut select = try!(con.prepare("SELECT state, letter, count FROM transition WHERE champion_id = ?"));
+        let transitions = try!(select.query_map(&[&(champion_id as i64)], |row| {
+            (row.get::<String>(0), row.get::<String>(1), row.get::<i64>(2))
+        }));
+        for transition in transitions {
+            let (state, letter, count) = try!(transition);
+            //States shorter than the order are the ones a nickname starts from.
+            let state = if state.chars().count() < champion.get_order() {
+                format!("{}{}", TERMINATOR, state)
+            } else {
+                state
+            };
+            let mut letters = letter.chars();
+            let letter = match (letters.next(), letters.next()) {
+                (letter, None) if count >= 0 => letter.unwrap_or(TERMINATOR),
+                _ => return Err(::store::corrupt(champion_id, format!("invalid transition from {:?}", state))),
+            };
+            champion.add_transition(&state, letter, count as u64);
+        }
+        Ok(Some(champion))
+    }
+
+    fn write_champion(con : &Connection, champion : &Champion) -> Result<(), StoreError> {
+        let champion_id = champion.get_id() as i64;
+        try!(con.execute("INSERT OR REPLACE INTO champion (id, chain_order) VALUES (?, ?)",
+                              &[&champion_id, &(champion.get_order() as i64)]));
+        try!(con.execute("DELETE FROM transition WHERE champion_id = ?", &[&champion_id]));
+        let mut insert = try!(con.prepare(
+            "INSERT INTO transition (champion_id, state, letter, count) VALUES (?, ?, ?, ?)"));
+        for (state, letter, count) in champion.transitions() {
+            let state : String = state.chars().filter(|&letter| letter != TERMINATOR).collect();
+            let letter = if letter == TERMINATOR { String::new() } else { letter.to_string() };
+            try!(insert.execute(&[&champion_id, &state, &letter, &(count as i64)]));
+        }
+        Ok(())
+    }
+
+    fn write_entries(con : &Connection, champion_id : u32, entries : &[Entry]) -> Result<(), StoreError> {
+        let mut insert = try!(con.prepare(
+            "INSERT OR IGNORE INTO nickname (champion_id, nickname, first_seen, realm, game_id) VALUES (?, ?, ?, ?, ?)"));
+        for entry in entries {
+            try!(insert.execute(&[&(champion_id as i64), &entry.nickname, &(entry.first_seen as i64), &entry.realm,
+                                  &entry.game_id.map(|game_id| game_id as i64)]));
+        }
+        Ok(())
+    }
+}
+
+impl ChampionStore for SqliteStore {
+    fn load(&self, champion_id : u32) -> Result<Option<Champion>, StoreError> {
+        SqliteStore::read_champion(&self.lock(), champion_id)
+    }
+
+    fn store(&self, champion : &Champion) -> Result<(), StoreError> {
+        self.transaction(|con| SqliteStore::write_champion(con, champion))
+    }
+
+    fn compare_and_store(&self, expected : Option<&Champion>, champion : &Champion) -> Result<bool, StoreError> {
+        let mut stored = false;
+        try!(self.transaction(|con| {
+            if try!(SqliteStore::read_champion(con, champion.get_id())).as_ref() == expected {
+                try!(SqliteStore::write_champion(con, champion));
+                stored = true;
+            }
+            Ok(())
+        }));
+        Ok(stored)
+    }
+
+    fn compare_and_store_batch(&self, batch : &[(Option<Champion>, Champion)], entries : &HashMap<u32, Vec<Entry>>)
+        -> Result<Vec<u32>, StoreError> {
+        let mut conflicts = Vec::new();
+        try!(self.transaction(|con| {
+            let mut current = Vec::new();
+            for &(_, ref champion) in batch {
+                current.push(try!(SqliteStore::read_champion(con, champion.get_id())));
+            }
+            conflicts = ::store::conflicts(batch, &current);
+            for &(_, ref champion) in batch {
+                if !conflicts.contains(&champion.get_id()) {
+                    try!(SqliteStore::write_champion(con, champion));
+                }
+            }
+            for (&champion_id, entries) in entries {
+                if !conflicts.contains(&champion_id) {
+                    try!(SqliteStore::write_entries(con, champion_id, entries));
+                }
+            }
+            Ok(())
+        }));
+        Ok(conflicts)
+    }
+
+    fn list(&self) -> Result<Vec<u32>, StoreError> {
+        let con = self.lock();
+        let mut select = try!(con.prepare("SELECT id FROM champion ORDER BY id"));
+        let rows = try!(select.query_map(&[], |row| row.get::<i64>(0)));
+        let mut champion_ids = Vec::new();
+        for champion_id in rows {
+            champion_ids.push(try!(champion_id) as u32);
+        }
+        Ok(champion_ids)
+    }
+
+    fn exists(&self, champion_id : u32) -> Result<bool, StoreError> {
+        let con = self.lock();
+        let mut select = try!(con.prepare("SELECT 1 FROM champion WHERE id = ?"));
+        let mut rows = try!(select.query_map(&[&(champion_id as i64)], |row| row.get::<i64>(0)));
+        Ok(rows.next().is_some())
+    }
+
+    fn delete(&self, champion_id : u32) -> Result<bool, StoreError> {
+        let champion_id = champion_id as i64;
+        let mut deleted = false;
+        try!(self.transaction(|con| {
+            try!(con.execute("DELETE FROM transition WHERE champion_id = ?", &[&champion_id]));
+            try!(con.execute("DELETE FROM nickname WHERE champion_id = ?", &[&champion_id]));
+            deleted = try!(con.execute("DELETE FROM champion WHERE id = ?", &[&champion_id])) > 0;
+            Ok(())
+        }));
+        Ok(deleted)
+    }
+
+    fn load_corpus(&self, champion_id : u32) -> Result<Corpus, StoreError> {
+        let mut corpus = Corpus::new(champion_id);
+        let con = self.lock();
+        let mut select = try!(con.prepare(
+            "SELECT nickname, first_seen, realm, game_id FROM nickname WHERE champion_id = ?"));
+        let entries = try!(select.query_map(&[&(champion_id as i64)], |row| {
+            Entry {
+                nickname : row.get(0),
+                first_seen : row.get::<i64>(1) as u64,
+                realm : row.get(2),
+                game_id : row.get::<Option<i64>>(3).map(|game_id| game_id as u64),
+            }
+        }));
+        for entry in entries {
+            corpus.add(try!(entry));
+        }
+        Ok(corpus)
+    }
+
+    fn add_to_corpus(&self, champion_id : u32, entries : &[Entry]) -> Result<(), StoreError> {
+        self.transaction(|con| SqliteStore::write_entries(con, champion_id, entries))
+    }
+
+    //Loading, feeding and storing happen in one transaction, so that concurrent feeds, from this process or others,
+    //can't lose updates.
+    fn feed(&self, champion_id : u32, entries : &[Entry]) -> Result<usize, StoreError> {
+        let mut fed = 0;
+        try!(self.transaction(|con| {
+            let mut champion = try!(SqliteStore::read_champion(con, champion_id))
+                .unwrap_or_else(|| Champion::new(champion_id));
+            fed = champion.feed_all(entries.iter().map(|entry| entry.nickname.as_str()));
+            try!(SqliteStore::write_champion(con, &champion));
+            SqliteStore::write_entries(con, champion_id, entries)
+        }));
+        Ok(fed)
+    }
+
+    fn load_registry(&self) -> Result<Option<ChampionRegistry>, StoreError> {
+        let con = self.lock();
+        let mut select = try!(con.prepare("SELECT data FROM registry WHERE id = 0"));
+        let data = match try!(select.query_map(&[], |row| row.get::<String>(0))).next() {
+            Some(data) => try!(data),
+            None => return Ok(None),
+        };
+        Ok(Some(try!(::store::parse_registry(&data))))
+    }
+
+    fn store_registry(&self, registry : &ChampionRegistry) -> Result<(), StoreError> {
+        let data = try!(registry.serialize());
+        try!(self.lock().execute("INSERT OR REPLACE INTO registry (id, data) VALUES (0, ?)", &[&data]));
+        Ok(())
+    }
+
+    fn store_many(&self, champions : &[Champion]) -> Result<(), StoreError> {
+        self.store_batch(champions, &HashMap::new())
+    }
+
+    fn store_batch(&self, champions : &[Champion], entries : &HashMap<u32, Vec<Entry>>) -> Result<(), StoreError> {
+        //Everything is written in a single transaction.
+        self.transaction(|con| {
+            for champion in champions {
+                try!(SqliteStore::write_champion(con, champion));
+            }
+            for (&champion_id, entries) in entries {
+                try!(SqliteStore::write_entries(con, champion_id, entries));
+            }
+            Ok(())
+        })
+    }
+}
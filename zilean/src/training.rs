@@ -0,0 +1,69 @@
+//! Trains many champions at once, on a pool of worker threads.
+//!
+//! Champions don't share any data, so each one is fed by a single worker and no lock is held while feeding.
+//!
+//! # Examples
+//!
+//! ```
+//! use self::zilean::champion::*;
+//! use self::zilean::training::*;
+//!
+//! let jobs = vec![
+//!     (Champion::new(10), vec!["foo".to_string(), "bar".to_string()]),
+//!     (Champion::new(11), vec!["baz".to_string()]),
+//! ];
+//! let champions = train_parallel(jobs, 4);
+//!
+//! assert_eq!(10, champions[0].get_id());
+//! assert_eq!(11, champions[1].get_id());
+//! ```
+use ::champion::Champion as Champion;
+use std::cmp;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Feeds each champion its nicknames, using at most `workers` threads.
+///
+/// Returns the trained champions, sorted by id.
+///
+/// # Arguments
+///
+/// * `jobs` : The champions, with the nicknames to feed them. A champion can already be trained.
+/// * `workers` : The number of threads, at least one is used.
+///
+/// # Panics
+///
+/// If a worker panicked.
+pub fn train_parallel(jobs : Vec<(Champion, Vec<String>)>, workers : usize) -> Vec<Champion> {
+    let workers = cmp::max(1, cmp::min(workers, jobs.len()));
+    //Workers pick the next job when they are done, so that a champion with many nicknames doesn't slow the others down.
+    let queue = Arc::new(Mutex::new(jobs.into_iter()));
+
+    let handles : Vec<_> = (0..workers).map(|_| {
+        let queue = queue.clone();
+        thread::spawn(move || {
+            let mut trained = Vec::new();
+            loop {
+                //The lock is released before feeding.
+                let job = match queue.lock() {
+                    Ok(mut queue) => queue.next(),
+                    Err(_) => None,
+                };
+                match job {
+                    Some((mut champion, nicknames)) => {
+                        champion.feed_all(&nicknames);
+                        trained.push(champion);
+                    },
+                    None => return trained,
+                }
+            }
+        })
+    }).collect();
+
+    let mut champions = Vec::new();
+    for handle in handles {
+        champions.extend(handle.join().expect("A training worker panicked"));
+    }
+    champions.sort_by_key(|champion| champion.get_id());
+    champions
+}
@@ -0,0 +1,51 @@
+extern crate zilean;
+
+use zilean::bulk::*;
+use zilean::database::Database as Database;
+
+#[test]
+fn test_bulk_reader() {
+    let file = "{ \"1\" : [\"a\\\"b\", \"\\u00c9ndz\", \"\\ud83d\\ude00\"],\n\"2\": [] }\n";
+    let pairs : Vec<_> = BulkReader::new(file.as_bytes()).map(|pair| pair.unwrap()).collect();
+    assert_eq!(vec![(1, vec!["a\"b".to_string(), "Éndz".to_string(), "😀".to_string()]), (2, vec![])], pairs);
+    assert_eq!(0, BulkReader::new("{}".as_bytes()).count());
+
+    for invalid in &["", "[]", "{\"foo\": []}", "{\"1\": [\"a\"]", "{\"1\": [\"a\"],}", "{\"1\": [2]}", "{} {}"] {
+        let res : Result<Vec<_>, _> = BulkReader::new(invalid.as_bytes()).collect();
+        match res {
+            Err(BulkError::Syntax(..)) => (),
+            res => panic!("{:?} : unexpected result {:?}", invalid, res),
+        }
+    }
+}
+
+#[test]
+fn test_import_bulk() {
+    let db = Database::new("memory://".to_string());
+    let options = BulkOptions {
+        batch_size : 10,
+        realm : Some("EUW".to_string()),
+        .. BulkOptions::default()
+    };
+    let report = import_bulk(&db, include_str!("../db.json").as_bytes(), &options).unwrap();
+    assert_eq!(105, report.total());
+    assert_eq!(65, report.nicknames.len());
+    assert_eq!(19, report.duplicates.len());
+    assert_eq!(Some(&2), report.nicknames.get(&150));
+    assert!(report.batches > 1);
+
+    //Both occurrences of "150" were fed, in different batches.
+    let corpus = db.load_corpus(150);
+    assert_eq!(2, corpus.len());
+    assert_eq!(Some("EUW".to_string()), corpus.get("Odoamne").unwrap().realm);
+    assert!(corpus.get("VG LeLeNaGa").is_some());
+    assert_eq!(2, Database::deserialize_bulk(include_str!("../db.json").to_string()).unwrap()[&150].len());
+}
+
+#[test]
+fn test_deserialize_bulk_invalid() {
+    match Database::deserialize_bulk("{\"1\": [2]}".to_string()) {
+        Err(BulkError::Syntax(..)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+}
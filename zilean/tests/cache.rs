@@ -0,0 +1,82 @@
+extern crate zilean;
+
+use zilean::cache::ChampionCache as ChampionCache;
+use zilean::champion::Champion as Champion;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+fn assert_send_sync<T : Send + Sync>() {}
+
+#[test]
+fn test_send_sync() {
+    assert_send_sync::<Champion>();
+    assert_send_sync::<ChampionCache>();
+}
+
+#[test]
+fn test_insert_get() {
+    let cache = ChampionCache::new();
+    assert!(cache.get(10).is_none());
+
+    let mut champion = Champion::new(10);
+    champion.feed("foo".to_string());
+    cache.insert(champion);
+    let cached = cache.get(10).unwrap();
+    assert_eq!(1, cached.stats().nicknames);
+
+    let mut updated = Champion::new(10);
+    updated.feed("foo".to_string());
+    updated.feed("bar".to_string());
+    cache.insert(updated);
+    assert_eq!(2, cache.get(10).unwrap().stats().nicknames);
+    //The previous champion is still usable by whoever holds it.
+    assert_eq!(1, cached.stats().nicknames);
+
+    cache.remove(10);
+    assert!(cache.get(10).is_none());
+}
+
+#[test]
+fn test_get_or_load() {
+    let cache = ChampionCache::new();
+    let loaded = cache.get_or_load(10, |id| Champion::new(id));
+    assert_eq!(10, loaded.get_id());
+    let cached = cache.get_or_load(10, |_| panic!("the champion should be cached"));
+    assert!(Arc::ptr_eq(&loaded, &cached));
+}
+
+#[test]
+fn test_ttl() {
+    let cache = ChampionCache::with_ttl(Duration::from_secs(60));
+    cache.insert(Champion::new(10));
+    assert!(cache.get(10).is_some());
+
+    //The sleep is well above the TTL, so that a slow machine can't make the test fail.
+    let cache = ChampionCache::with_ttl(Duration::from_millis(20));
+    let cached = cache.insert(Champion::new(10));
+    thread::sleep(Duration::from_millis(200));
+    assert!(cache.get(10).is_none());
+    //Expired champions are loaded again.
+    let loaded = cache.get_or_load(10, |id| Champion::new(id));
+    assert!(!Arc::ptr_eq(&cached, &loaded));
+}
+
+#[test]
+fn test_concurrent_gen() {
+    let cache = Arc::new(ChampionCache::new());
+    let mut champion = Champion::new(10);
+    champion.feed("foo".to_string());
+    cache.insert(champion);
+
+    let workers : Vec<_> = (0..8).map(|_| {
+        let cache = cache.clone();
+        thread::spawn(move || {
+            let champion = cache.get(10).unwrap();
+            (0..100).map(|_| champion.gen(16).unwrap()).count()
+        })
+    }).collect();
+    for worker in workers {
+        assert_eq!(100, worker.join().unwrap());
+    }
+}
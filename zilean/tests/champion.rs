@@ -2,7 +2,9 @@ extern crate zilean;
 extern crate serde_json;
 
 use zilean::champion::Champion as Champion;
-use std::collections::HashMap;
+use zilean::champion::GenError as GenError;
+use zilean::database::Database as Database;
+use std::time::Instant;
 
 #[test]
 fn test_get_id() {
@@ -46,6 +48,197 @@ fn test_eq() {
     assert_eq!(champion, champion2);
 }
 
+#[test]
+fn test_feed_counts() {
+    let mut champion = Champion::new(10);
+    assert!(champion.feed("ab"));
+    assert!(champion.feed("ac".to_string()));
+    assert!(!champion.feed("ab"));
+    assert!(!champion.feed(""));
+
+    let mut expected = Champion::new(10);
+    expected.deserialize(r#"{"\u0000": {"a": 2}, "a": {"b": 1, "c": 1}, "b": {"\u0000": 1}, "c": {"\u0000": 1}}"#.to_string());
+    assert_eq!(expected, champion);
+}
+
+#[test]
+fn test_feed_all() {
+    let nicknames = vec!["foo", "bar", "baz", "foo", "quux"];
+    let mut champion = Champion::with_order(10, 2);
+    assert_eq!(4, champion.feed_all(nicknames.iter().cloned()));
+
+    let mut champion2 = Champion::with_order(10, 2);
+    for nickname in nicknames {
+        champion2.feed(nickname.to_string());
+    }
+    assert_eq!(champion, champion2);
+}
+
+#[test]
+fn test_feed_throughput() {
+    let bulk = Database::deserialize_bulk(include_str!("../db.json").to_string()).unwrap();
+    let nicknames : Vec<&str> = bulk.values().flat_map(|nicknames| nicknames.iter().map(|nickname| nickname.as_str())).collect();
+
+    for order in 1..4 {
+        let mut champion = Champion::with_order(0, order);
+        let start = Instant::now();
+        champion.feed_all(nicknames.iter().cloned());
+        let elapsed = start.elapsed();
+        let seconds = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+        println!("Order {} : fed {} nicknames in {:.3}s ({:.0} nicknames/s)",
+                 order, nicknames.len(), seconds, nicknames.len() as f64 / seconds.max(1e-9));
+        assert_eq!(Vec::<String>::new(), champion.validate().dead_ends);
+    }
+}
+
+#[test]
+fn test_gen_not_fed() {
+    let champion = Champion::new(10);
+    assert_eq!(Err(GenError::EmptyModel), champion.gen(10));
+}
+
+#[test]
+fn test_gen_dead_end() {
+    //"b" is a successor of "a" but has no successors itself.
+    let mut champion = Champion::new(10);
+    champion.deserialize(r#"{"\u0000": {"a": 1}, "a": {"b": 1}}"#.to_string());
+    assert_eq!(Ok("ab".to_string()), champion.gen(10));
+}
+
+#[test]
+fn test_validate() {
+    let mut champion = Champion::new(10);
+    champion.feed("foo".to_string());
+    champion.feed("bar".to_string());
+    assert!(champion.validate().is_valid());
+
+    champion.deserialize(r#"{"\u0000": {"a": 1}, "a": {"b": 1, "\u0000": 1}, "c": {"a": 1}}"#.to_string());
+    let validation = champion.validate();
+    assert_eq!(vec!["b".to_string()], validation.dead_ends);
+    assert_eq!(vec!["c".to_string()], validation.unreachable);
+}
+
+#[test]
+fn test_stats() {
+    let mut champion = Champion::new(10);
+    champion.feed("foo".to_string());
+    champion.feed("bar".to_string());
+    champion.feed("baz".to_string());
+
+    let stats = champion.stats();
+    assert_eq!(3, stats.nicknames);
+    assert_eq!(3.0, stats.average_length);
+    assert_eq!(vec![('b', 2), ('f', 1)], stats.starts);
+    assert_eq!(vec![('o', 1), ('r', 1), ('z', 1)], stats.ends);
+    assert_eq!(0.0, stats.entropy["f"]);
+    assert_eq!(1.0, stats.entropy["a"]);
+}
+
+#[test]
+fn test_to_dot() {
+    let mut champion = Champion::new(10);
+    champion.feed("ab".to_string());
+    champion.feed("ac".to_string());
+    champion.feed("ac".to_string());
+
+    let dot = champion.to_dot(None);
+    assert!(dot.starts_with("digraph champion_10 {"));
+    assert!(dot.contains("\"START\" -> \"a\" [label=\"1.000\", weight=2];"));
+    assert!(dot.contains("\"a\" -> \"b\" [label=\"0.500\", weight=1];"));
+    assert!(dot.contains("\"c\" -> \"END\" [label=\"1.000\", weight=1];"));
+
+    let pruned = champion.to_dot(Some(1));
+    assert_eq!(1, pruned.matches("\"a\" -> ").count());
+}
+
+#[test]
+fn test_gen_order() {
+    let mut champion = Champion::with_order(10, 3);
+    champion.feed("Hello world".to_string());
+    assert_eq!(3, champion.get_order());
+    //Every state of 3 letters has a single successor, so there's only one way to generate.
+    assert_eq!(Ok("Hello world".to_string()), champion.gen(20));
+    assert!(champion.validate().is_valid());
+}
+
+#[test]
+fn test_stats_order() {
+    let mut champion = Champion::with_order(10, 2);
+    champion.feed("foo".to_string());
+    champion.feed("bar".to_string());
+    champion.feed("baz".to_string());
+
+    let stats = champion.stats();
+    assert_eq!(3, stats.nicknames);
+    assert_eq!(3.0, stats.average_length);
+    assert_eq!(vec![('b', 2), ('f', 1)], stats.starts);
+    assert_eq!(vec![('o', 1), ('r', 1), ('z', 1)], stats.ends);
+}
+
+#[test]
+#[should_panic]
+fn test_order_zero() {
+    Champion::with_order(10, 0);
+}
+
+#[test]
+fn test_top() {
+    let mut champion = Champion::new(10);
+    champion.feed("ab".to_string());
+    champion.feed("ac".to_string());
+    champion.feed("acd".to_string());
+
+    let top = champion.top(10, 1, 16);
+    let nicknames : Vec<&str> = top.iter().map(|&(ref nickname, _)| nickname.as_str()).collect();
+    //"a" is followed by "b" once and "c" twice, "c" ends the nickname or is followed by "d" : all are equally likely.
+    assert_eq!(vec!["ab", "ac", "acd"], nicknames);
+    assert!(top.iter().all(|&(_, probability)| (probability - 1.0 / 3.0).abs() < 1e-9));
+
+    assert_eq!(vec![("ab".to_string(), top[0].1)], champion.top(1, 1, 16));
+    assert_eq!("acd", champion.top(10, 3, 16)[0].0);
+    assert_eq!(2, champion.top(10, 1, 2).len());
+    assert!(Champion::new(10).top(10, 1, 16).is_empty());
+
+    //A chain with many likely nicknames still gives the most likely ones, in order.
+    let mut champion = Champion::new(11);
+    for (_, nicknames) in Database::deserialize_bulk(include_str!("../db.json").to_string()).unwrap() {
+        champion.feed_all(nicknames);
+    }
+    let top = champion.top(100, 1, 16);
+    assert_eq!(100, top.len());
+    assert!(top.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+    //Every letter can follow every letter : the search drops candidates instead of growing without bound.
+    let letters = "abcdefghijklmnopqrstuvwxyz0123456789";
+    let mut champion = Champion::new(12);
+    for first in letters.chars() {
+        champion.feed_all(letters.chars().map(|second| format!("{}{}", first, second)));
+    }
+    let top = champion.top(10, 3, 16);
+    assert_eq!(10, top.len());
+    assert!(top.iter().all(|&(ref nickname, _)| nickname.chars().count() >= 3));
+}
+
+#[test]
+fn test_nicknames() {
+    let mut champion = Champion::with_order(10, 3);
+    champion.feed("foo".to_string());
+    champion.feed("bar".to_string());
+    champion.feed("  ".to_string());
+
+    assert_eq!(10, champion.nicknames(16).take(10).count());
+    assert_eq!(0, Champion::new(10).nicknames(16).count());
+
+    //An order 3 chain only knows these nicknames, so the iterator ends once it has seen them all.
+    let mut unique : Vec<String> = champion.nicknames(16).unique().collect();
+    unique.sort();
+    assert_eq!(vec!["  ", "bar", "foo"], unique);
+
+    let valid : Vec<String> = champion.nicknames(16).unique().valid(1).collect();
+    assert_eq!(2, valid.len());
+    assert!(champion.nicknames(16).novel(vec!["foo".to_string(), "bar".to_string()]).take(5).all(|nickname| nickname == "  "));
+}
+
 #[should_panic]
 fn test_gen_2_times() {
     let mut champion = Champion::new(10);
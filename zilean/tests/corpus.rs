@@ -0,0 +1,50 @@
+extern crate zilean;
+
+use zilean::corpus::{Corpus, Entry};
+
+fn entry(nickname : &str, first_seen : u64) -> Entry {
+    Entry {
+        nickname : nickname.to_string(),
+        first_seen : first_seen,
+        realm : Some("EUW".to_string()),
+        game_id : Some(2578251061),
+    }
+}
+
+#[test]
+fn test_add() {
+    let mut corpus = Corpus::new(10);
+    assert!(corpus.add(entry("foo", 20)));
+    assert!(corpus.add(entry("bar", 30)));
+    assert!(!corpus.add(entry("foo", 10)));
+    assert!(!corpus.add(entry("", 10)));
+
+    assert_eq!(2, corpus.len());
+    assert_eq!(10, corpus.get("foo").unwrap().first_seen);
+    let nicknames : Vec<&str> = corpus.entries().iter().map(|entry| entry.nickname.as_str()).collect();
+    assert_eq!(vec!["foo", "bar"], nicknames);
+}
+
+#[test]
+fn test_entry_serialize() {
+    let seen = entry("foo", 20);
+    assert_eq!(seen, Entry::deserialize("foo".to_string(), &seen.serialize().unwrap()).unwrap());
+
+    let unknown = Entry::new("bar".to_string());
+    assert_eq!(unknown, Entry::deserialize("bar".to_string(), &unknown.serialize().unwrap()).unwrap());
+}
+
+#[test]
+fn test_train() {
+    let mut corpus = Corpus::new(10);
+    corpus.add(entry("foo", 20));
+    corpus.add(entry("bar", 30));
+
+    let champion = corpus.train(2);
+    assert_eq!(10, champion.get_id());
+    assert_eq!(2, champion.get_order());
+    assert_eq!(2, champion.stats().nicknames);
+
+    let filtered = corpus.train_filtered(1, |entry| entry.first_seen > 25);
+    assert_eq!(1, filtered.stats().nicknames);
+}
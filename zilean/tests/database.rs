@@ -1,12 +1,34 @@
+extern crate redis;
+extern crate rusqlite;
 extern crate zilean;
 
 use zilean::database::Database as Database;
 use zilean::champion::Champion as Champion;
+use zilean::corpus::{Corpus, Entry};
+use zilean::registry::ChampionRegistry as ChampionRegistry;
+use zilean::store::{ChampionStore, ConnectError, StoreError};
+use zilean::store::file_store::FileStore as FileStore;
+use zilean::store::memory_store::MemoryStore as MemoryStore;
+use zilean::store::sqlite_store::SqliteStore as SqliteStore;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use redis::Commands;
+
+//Tests run in memory, unless DATABASE_URL is set, like DATABASE_URL=redis://redis.
+fn database() -> Database {
+    Database::new(env::var("DATABASE_URL").unwrap_or("memory://".to_string()))
+}
 
-const REDIS_URL : &'static str = "redis://redis";
 #[test]
 fn test_new() {
-    let db = Database::new(REDIS_URL.to_string());
+    let db = database();
 }
 
 #[test]
@@ -15,35 +37,477 @@ fn test_new_invalid_url() {
     let db = Database::new("radis://265.4.2.4".to_string());
 }
 
+#[test]
+fn test_connect_errors() {
+    match Database::connect("radis://265.4.2.4".to_string()) {
+        Err(ConnectError::InvalidUrl(_)) => (),
+        _ => panic!("radis isn't a backend"),
+    }
+    let path = env::temp_dir().join(format!("zilean-missing-{}", process::id())).join("zelkow.sqlite");
+    match Database::connect(format!("sqlite://{}", path.display())) {
+        Err(err) => assert!(err.is_temporary()),
+        Ok(_) => panic!("the directory of the database doesn't exist"),
+    }
+}
+
 #[test]
 fn test_load_store() {
-    let db = Database::new(REDIS_URL.to_string());
+    let db = database();
     let mut champion = Champion::new(10);
     champion.feed("foo".to_string());
     champion.feed("bar".to_string());
     db.store(&champion);
 
-    let champion2 = db.load(10);
+    let champion2 = db.load(10).unwrap();
 
-    assert_eq!(champion, champion2);
+    assert_eq!(Some(champion), champion2);
 }
 
 #[test]
 fn test_update() {
-    let db = Database::new(REDIS_URL.to_string());
-    let mut champion = Champion::new(10);
+    let db = database();
+    let mut champion = Champion::new(15);
     champion.feed("foo".to_string());
     champion.feed("bar".to_string());
     champion.feed("baz".to_string());
 
-    // let mut champion2 = Champion::new(10);
-    // champion2.feed("foo".to_string());
-    // champion2.feed("bar".to_string());
-    //
-    // db.store(champion2);
-    // champion2 = db.load(10);
-    // champion2.feed("baz".to_string());
-    // db.store(champion2);
-    //
-    // assert_eq!(champion, db.load(10));
+    let mut champion2 = Champion::new(15);
+    champion2.feed("foo".to_string());
+    champion2.feed("bar".to_string());
+
+    db.store(&champion2).unwrap();
+    champion2 = db.load(15).unwrap().unwrap();
+    champion2.feed("baz".to_string());
+    db.store(&champion2).unwrap();
+
+    assert_eq!(Some(champion), db.load(15).unwrap());
+}
+
+#[test]
+fn test_compare_and_store() {
+    let db = database();
+    db.delete(23).unwrap();
+    let empty = Champion::new(23);
+    let mut champion = Champion::new(23);
+    champion.feed("foo");
+    assert!(db.compare_and_store(None, &empty).unwrap());
+    assert!(!db.compare_and_store(None, &champion).unwrap());
+    assert!(db.compare_and_store(Some(&empty), &champion).unwrap());
+    assert!(!db.compare_and_store(Some(&empty), &empty).unwrap());
+    assert_eq!(Some(champion), db.load(23).unwrap());
+}
+
+#[test]
+fn test_concurrent_updates() {
+    let db = Arc::new(database());
+    db.delete(24).unwrap();
+    let threads : Vec<_> = (0..4).map(|_| {
+        let db = db.clone();
+        thread::spawn(move || {
+            for _ in 0..20 {
+                //Each update counts one more transition, none may be lost.
+                let increment = |current : Option<&Champion>| {
+                    let mut champion = Champion::new(24);
+                    for (state, letter, count) in current.map(|current| current.transitions()).unwrap_or(Vec::new()) {
+                        champion.add_transition(&state, letter, count);
+                    }
+                    champion.add_transition("a", 'b', 1);
+                    Some(champion)
+                };
+                loop {
+                    match db.update(24, &increment) {
+                        Err(StoreError::Conflict(24)) => (),
+                        res => {
+                            res.unwrap();
+                            break;
+                        },
+                    }
+                }
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(vec![("a".to_string(), 'b', 80)], db.load(24).unwrap().unwrap().transitions());
+}
+
+#[test]
+fn test_load_store_registry() {
+    let db = database();
+    let registry = ChampionRegistry::from_static_data(include_str!("../../twitch/dbg_champs.json")).unwrap();
+    db.store_registry(&registry).unwrap();
+
+    assert_eq!(Some(registry), db.load_registry());
+}
+
+#[test]
+fn test_corpus_rebuild() {
+    let db = database();
+    let entries = vec![Entry::new("foo".to_string()), Entry::new("bar".to_string())];
+    db.add_to_corpus(11, &entries).unwrap();
+    db.add_to_corpus(11, &entries).unwrap();
+
+    let corpus = db.load_corpus(11);
+    assert_eq!(2, corpus.len());
+
+    let champion = db.rebuild(11, 2).unwrap();
+    assert_eq!(2, champion.get_order());
+    assert_eq!(corpus.train(2), champion);
+    assert_eq!(Some(champion), db.load(11).unwrap());
+}
+
+#[test]
+fn test_train_bulk() {
+    let db = database();
+    let mut bulk = HashMap::new();
+    bulk.insert(12, vec![Entry::new("foo".to_string()), Entry::new("bar".to_string())]);
+    bulk.insert(13, vec![Entry::new("baz".to_string())]);
+    let champions = db.train_bulk(bulk, 4).unwrap();
+
+    assert_eq!(vec![12, 13], champions.iter().map(|champion| champion.get_id()).collect::<Vec<u32>>());
+    assert_eq!(champions, db.load_many(&[12, 13]).unwrap());
+    assert!(db.load_corpus(12).get("bar").is_some());
+}
+
+//Feeds the champions it loads in bulk, like another coordinator feeding them while `train_bulk` trains, a new
+//nickname each time, until it ran `races` times.
+struct RacingStore(MemoryStore, AtomicUsize);
+
+impl RacingStore {
+    fn new(races : usize) -> RacingStore {
+        RacingStore(MemoryStore::new(), AtomicUsize::new(races))
+    }
+}
+
+impl ChampionStore for RacingStore {
+    fn load(&self, champion_id : u32) -> Result<Option<Champion>, StoreError> {
+        self.0.load(champion_id)
+    }
+
+    fn store(&self, champion : &Champion) -> Result<(), StoreError> {
+        self.0.store(champion)
+    }
+
+    fn compare_and_store(&self, expected : Option<&Champion>, champion : &Champion) -> Result<bool, StoreError> {
+        self.0.compare_and_store(expected, champion)
+    }
+
+    fn compare_and_store_batch(&self, batch : &[(Option<Champion>, Champion)], entries : &HashMap<u32, Vec<Entry>>)
+        -> Result<Vec<u32>, StoreError> {
+        self.0.compare_and_store_batch(batch, entries)
+    }
+
+    fn list(&self) -> Result<Vec<u32>, StoreError> {
+        self.0.list()
+    }
+
+    fn delete(&self, champion_id : u32) -> Result<bool, StoreError> {
+        self.0.delete(champion_id)
+    }
+
+    fn load_corpus(&self, champion_id : u32) -> Result<Corpus, StoreError> {
+        self.0.load_corpus(champion_id)
+    }
+
+    fn add_to_corpus(&self, champion_id : u32, entries : &[Entry]) -> Result<(), StoreError> {
+        self.0.add_to_corpus(champion_id, entries)
+    }
+
+    fn load_registry(&self) -> Result<Option<ChampionRegistry>, StoreError> {
+        self.0.load_registry()
+    }
+
+    fn store_registry(&self, registry : &ChampionRegistry) -> Result<(), StoreError> {
+        self.0.store_registry(registry)
+    }
+
+    fn feed(&self, champion_id : u32, entries : &[Entry]) -> Result<usize, StoreError> {
+        self.0.feed(champion_id, entries)
+    }
+
+    fn load_many(&self, champion_ids : &[u32]) -> Result<Vec<Option<Champion>>, StoreError> {
+        let champions = try!(self.0.load_many(champion_ids));
+        let races = self.1.load(Ordering::SeqCst);
+        if races > 0 {
+            self.1.store(races - 1, Ordering::SeqCst);
+            for &champion_id in champion_ids {
+                try!(self.0.feed(champion_id, &[Entry::new(format!("qux{}", races))]));
+            }
+        }
+        Ok(champions)
+    }
+}
+
+#[test]
+fn test_train_bulk_concurrent_feed() {
+    let db = Database::with_store("memory://".to_string(), Box::new(RacingStore::new(1)));
+    let mut bulk = HashMap::new();
+    bulk.insert(12, vec![Entry::new("foo".to_string())]);
+    bulk.insert(13, vec![Entry::new("bar".to_string())]);
+    let champions = db.train_bulk(bulk, 4).unwrap();
+
+    //What was fed while training is kept, the champions are trained again.
+    let mut expected = Champion::new(12);
+    expected.feed_all(vec!["qux1", "foo"]);
+    assert_eq!(expected, champions[0]);
+    assert_eq!(champions, db.load_many(&[12, 13]).unwrap());
+    assert_eq!(2, db.load_corpus(12).len());
+    assert_eq!(2, db.load_corpus(13).len());
+}
+
+#[test]
+fn test_train_bulk_conflicts() {
+    //Fed while each attempt of `train_bulk` trains, so that the nicknames end up fed to the stored champion.
+    let db = Database::with_store("memory://".to_string(), Box::new(RacingStore::new(10)));
+    let mut bulk = HashMap::new();
+    bulk.insert(12, vec![Entry::new("foo".to_string())]);
+    let champions = db.train_bulk(bulk, 4).unwrap();
+
+    let mut expected = Champion::new(12);
+    expected.feed_all((1..11).rev().map(|race| format!("qux{}", race)));
+    expected.feed("foo");
+    assert_eq!(vec![expected], champions);
+    assert_eq!(champions, db.load_many(&[12]).unwrap());
+    assert_eq!(11, db.load_corpus(12).len());
+}
+
+//Champions still stored as expected are written with their nicknames, the others are left alone.
+fn check_compare_and_store_batch(store : &ChampionStore) {
+    store.delete(27).unwrap();
+    store.delete(28).unwrap();
+    let mut stored = Champion::new(27);
+    stored.feed("foo");
+    store.store(&stored).unwrap();
+
+    let mut first = Champion::new(27);
+    first.feed("bar");
+    let mut second = Champion::new(28);
+    second.feed("baz");
+    let batch = vec![(None, first), (None, second.clone())];
+    let mut entries = HashMap::new();
+    entries.insert(27, vec![Entry::new("bar".to_string())]);
+    entries.insert(28, vec![Entry::new("baz".to_string())]);
+    assert_eq!(vec![27], store.compare_and_store_batch(&batch, &entries).unwrap());
+    assert_eq!(vec![Some(stored), Some(second)], store.load_many(&[27, 28]).unwrap());
+    assert_eq!(0, store.load_corpus(27).unwrap().len());
+    assert_eq!(1, store.load_corpus(28).unwrap().len());
+}
+
+#[test]
+fn test_compare_and_store_batch() {
+    check_compare_and_store_batch(&MemoryStore::new());
+
+    let dir = env::temp_dir().join(format!("zilean-batch-{}", process::id()));
+    check_compare_and_store_batch(&FileStore::open(&dir).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+
+    let path = env::temp_dir().join(format!("zilean-batch-{}.sqlite", process::id()));
+    check_compare_and_store_batch(&SqliteStore::open(&path).unwrap());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_list_delete() {
+    let db = database();
+    let mut champion = Champion::new(14);
+    champion.feed("foo");
+    db.store(&champion).unwrap();
+    db.add_to_corpus(14, &[Entry::new("foo".to_string())]).unwrap();
+    assert!(db.list().unwrap().contains(&14));
+    assert!(db.exists(14).unwrap());
+
+    //Resetting keeps the corpus, so the champion can be rebuilt.
+    assert!(db.reset(14).unwrap());
+    assert_eq!(Some(Champion::new(14)), db.load(14).unwrap());
+    assert_eq!(champion, db.rebuild(14, 1).unwrap());
+
+    assert!(db.delete(14).unwrap());
+    assert!(!db.delete(14).unwrap());
+    assert!(!db.list().unwrap().contains(&14));
+    assert_eq!(None, db.load(14).unwrap());
+    assert!(db.load_corpus(14).is_empty());
+    assert!(!db.exists(14).unwrap());
+    assert!(!db.reset(14).unwrap());
+}
+
+#[test]
+fn test_file_store() {
+    let dir = env::temp_dir().join(format!("zilean-test-file-store-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let url = format!("file://{}", dir.display());
+    let mut champion = Champion::with_order(16, 2);
+    champion.feed("foo");
+    {
+        let db = Database::new(url.clone());
+        db.store(&champion).unwrap();
+        db.add_to_corpus(16, &[Entry::new("foo".to_string())]).unwrap();
+        db.store(&Champion::new(17)).unwrap();
+    }
+
+    //Everything is still there once the database is opened again.
+    let db = Database::new(url);
+    assert_eq!(Some(champion), db.load(16).unwrap());
+    assert!(db.load_corpus(16).get("foo").is_some());
+    assert_eq!(vec![16, 17], db.list().unwrap());
+    assert!(db.delete(17).unwrap());
+    assert_eq!(vec![16], db.list().unwrap());
+
+    //Temporary files were all renamed.
+    for file in fs::read_dir(dir.join("champions")).unwrap() {
+        assert!(file.unwrap().path().extension().unwrap() == "json");
+    }
+
+    //Damaged files are reported, not loaded as missing champions.
+    fs::File::create(dir.join("champions").join("17.json")).unwrap().write_all(b"{\"a\": ").unwrap();
+    match db.load(17) {
+        Err(StoreError::Corrupt(_)) => (),
+        other => panic!("Expected corrupt data, got {:?}", other),
+    }
+    assert!(db.feed(17, &[Entry::new("foo".to_string())]).is_err());
+
+    //A damaged registry is reported, except by the lenient `load_registry`.
+    fs::File::create(dir.join("registry.json")).unwrap().write_all(b"{").unwrap();
+    match db.try_load_registry() {
+        Err(StoreError::Corrupt(_)) => (),
+        other => panic!("Expected corrupt data, got {:?}", other),
+    }
+    assert!(db.load_registry().is_none());
+
+    //A damaged corpus doesn't wipe the champion it would have trained.
+    fs::File::create(dir.join("corpus").join("16.json")).unwrap().write_all(b"[").unwrap();
+    assert!(db.rebuild(16, 2).is_err());
+    assert_eq!(1, db.load(16).unwrap().unwrap().stats().nicknames);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_file_store_shared() {
+    //Each database opens the directory on its own, like two coordinators would.
+    let dir = env::temp_dir().join(format!("zilean-test-file-store-shared-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let url = format!("file://{}", dir.display());
+    let threads : Vec<_> = (0..4).map(|i| {
+        let db = Database::new(url.clone());
+        thread::spawn(move || {
+            for j in 0..10 {
+                db.feed(26, &[Entry::new(format!("foo{}{}", i, j))]).unwrap();
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    let db = Database::new(url);
+    assert_eq!(40, db.load(26).unwrap().unwrap().stats().nicknames);
+    assert_eq!(40, db.load_corpus(26).len());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sqlite_store() {
+    let path = env::temp_dir().join(format!("zilean-test-sqlite-store-{}.sqlite", process::id()));
+    let _ = fs::remove_file(&path);
+    let url = format!("sqlite://{}", path.display());
+    let mut champion = Champion::with_order(18, 3);
+    champion.feed_all(vec!["foo", "bar", "fox"]);
+    {
+        let db = Database::new(url.clone());
+        db.store(&champion).unwrap();
+        db.add_to_corpus(18, &[Entry::new("foo".to_string()), Entry::new("bar".to_string())]).unwrap();
+        db.store(&Champion::new(19)).unwrap();
+    }
+
+    let db = Database::new(url);
+    assert_eq!(Some(champion), db.load(18).unwrap());
+    assert_eq!(2, db.load_corpus(18).len());
+    assert_eq!(vec![18, 19], db.list().unwrap());
+    assert!(db.delete(19).unwrap());
+    assert_eq!(vec![18], db.list().unwrap());
+
+    //The first letters can be queried directly.
+    let con = rusqlite::Connection::open(&path).unwrap();
+    let mut select = con.prepare("SELECT letter, count FROM start WHERE champion_id = 18 ORDER BY letter").unwrap();
+    let starts : Vec<(String, i64)> = select.query_map(&[], |row| (row.get(0), row.get(1))).unwrap()
+        .map(|start| start.unwrap())
+        .collect();
+    assert_eq!(vec![("b".to_string(), 1), ("f".to_string(), 2)], starts);
+
+    //Writers wait for other processes to release the database.
+    con.execute_batch("BEGIN IMMEDIATE").unwrap();
+    let writer = thread::spawn(move || db.store(&Champion::new(19)));
+    thread::sleep(Duration::from_millis(200));
+    con.execute_batch("COMMIT").unwrap();
+    writer.join().unwrap().unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_feed() {
+    let db = database();
+    let mut champion = Champion::new(20);
+    champion.feed("foo");
+    db.store(&champion).unwrap();
+
+    let entries = vec![Entry::new("bar".to_string()), Entry::new("foo".to_string()), Entry::new("baz".to_string())];
+    assert_eq!(2, db.feed(20, &entries).unwrap());
+    champion.feed_all(vec!["bar", "foo", "baz"]);
+    assert_eq!(Some(champion), db.load(20).unwrap());
+    assert_eq!(3, db.load_corpus(20).len());
+
+    //Feeding an unknown champion creates it.
+    assert_eq!(1, db.feed(21, &entries[..1]).unwrap());
+    assert_eq!(1, db.load(21).unwrap().unwrap().stats().nicknames);
+}
+
+#[test]
+fn test_concurrent_feeds() {
+    let db = Arc::new(database());
+    db.delete(25).unwrap();
+    let threads : Vec<_> = (0..4).map(|i| {
+        let db = db.clone();
+        thread::spawn(move || {
+            for j in 0..10 {
+                db.feed(25, &[Entry::new(format!("foo{}{}", i, j))]).unwrap();
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    //No feed was lost.
+    assert_eq!(40, db.load(25).unwrap().unwrap().stats().nicknames);
+    assert_eq!(40, db.load_corpus(25).len());
+    db.delete(25).unwrap();
+}
+
+#[test]
+fn test_migrate() {
+    //Only Redis has something to migrate.
+    let url = env::var("DATABASE_URL").unwrap_or(String::new());
+    if !url.starts_with("redis://") {
+        assert_eq!(0, database().migrate().unwrap());
+        return;
+    }
+    let con = redis::Client::open(url.as_str()).unwrap().get_connection().unwrap();
+    let mut champion = Champion::new(22);
+    champion.feed("foo");
+    let _ : () = con.set("22", champion.serialize().unwrap()).unwrap();
+    let _ : () = con.hset("corpus:22", "foo", Entry::new("foo".to_string()).serialize().unwrap()).unwrap();
+    //Keys of other applications, only named like legacy ones.
+    let _ : () = con.set("23", "foo").unwrap();
+    let _ : () = con.hset("corpus:23", "foo", "bar").unwrap();
+
+    let db = Database::new(format!("{}?namespace=test-migrate", url.trim_right_matches('/')));
+    assert_eq!(2, db.migrate().unwrap());
+    assert_eq!(0, db.migrate().unwrap());
+    assert_eq!(Some(champion), db.load(22).unwrap());
+    assert_eq!(1, db.load_corpus(22).len());
+    assert_eq!(vec![22], db.list().unwrap());
+    let exists : bool = con.exists("22").unwrap();
+    assert!(!exists);
+    let kept : (bool, bool) = (con.exists("23").unwrap(), con.exists("corpus:23").unwrap());
+    assert_eq!((true, true), kept);
+    let _ : () = con.del(&["23", "corpus:23"][..]).unwrap();
+    db.delete(22).unwrap();
 }
@@ -0,0 +1,63 @@
+extern crate zilean;
+
+use zilean::corpus::{Corpus, Entry};
+use zilean::database::Database as Database;
+use zilean::distance::edit_distance;
+use zilean::evaluation::*;
+
+//Every nickname of db.json, whatever the champion.
+fn load_corpus() -> Corpus {
+    let mut corpus = Corpus::new(0);
+    for (_, nicknames) in Database::deserialize_bulk(include_str!("../db.json").to_string()).unwrap() {
+        for nickname in nicknames {
+            corpus.add(Entry::new(nickname));
+        }
+    }
+    corpus
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(0, edit_distance("foo", "foo"));
+    assert_eq!(3, edit_distance("", "foo"));
+    assert_eq!(1, edit_distance("foo", "fo"));
+    assert_eq!(1, edit_distance("Yasuo", "Yasou"));
+    assert_eq!(3, edit_distance("kitten", "sitting"));
+}
+
+#[test]
+fn test_split() {
+    let corpus = load_corpus();
+    let (train, test) = split(&corpus, 0.2, 42);
+    assert_eq!(corpus.len(), train.len() + test.len());
+    assert_eq!((corpus.len() as f64 * 0.2).round() as usize, test.len());
+    assert!(test.entries().iter().all(|entry| train.get(&entry.nickname).is_none()));
+    assert_eq!((train.clone(), test.clone()), split(&corpus, 0.2, 42));
+}
+
+#[test]
+fn test_evaluate() {
+    let corpus = load_corpus();
+    let mut parameters = Parameters::default();
+    for order in 1..4 {
+        parameters.order = order;
+        let report = evaluate(&corpus, &parameters);
+        assert_eq!(corpus.len(), report.train_size + report.test_size);
+        assert!(report.perplexity > 1.0 && report.perplexity.is_finite());
+        assert_eq!(parameters.samples, report.generated);
+        assert!(report.novelty_rate >= 0.0 && report.novelty_rate <= 1.0);
+    }
+}
+
+#[test]
+fn test_evaluate_without_smoothing() {
+    let mut corpus = Corpus::new(0);
+    corpus.add(Entry::new("foo".to_string()));
+    corpus.add(Entry::new("bar".to_string()));
+    let mut parameters = Parameters::default();
+    parameters.smoothing = 0.0;
+    parameters.test_ratio = 0.5;
+
+    //Whichever is kept out, the model can't generate it.
+    assert!(evaluate(&corpus, &parameters).perplexity.is_infinite());
+}
@@ -0,0 +1,64 @@
+extern crate zilean;
+
+use zilean::registry::ChampionRegistry as ChampionRegistry;
+
+const STATIC_DATA : &'static str = include_str!("../../twitch/dbg_champs.json");
+
+#[test]
+fn test_from_static_data() {
+    let registry = ChampionRegistry::from_static_data(STATIC_DATA).unwrap();
+    assert_eq!(129, registry.len());
+    assert_eq!("6.5.1", registry.get_version());
+
+    let mundo = registry.get(36).unwrap();
+    assert_eq!("DrMundo", mundo.key);
+    assert_eq!("Dr. Mundo", mundo.name);
+    assert_eq!("the Madman of Zaun", mundo.title);
+}
+
+#[test]
+fn test_lookup() {
+    let registry = ChampionRegistry::from_static_data(STATIC_DATA).unwrap();
+    assert!(registry.contains(157));
+    assert!(!registry.contains(0));
+    assert_eq!(Some(62), registry.id_by_key("MonkeyKing"));
+    assert_eq!(Some(62), registry.id_by_name("Wukong"));
+    assert_eq!(None, registry.id_by_name("wukong"));
+}
+
+#[test]
+fn test_find_by_name() {
+    let registry = ChampionRegistry::from_static_data(STATIC_DATA).unwrap();
+    assert_eq!(157, registry.find_by_name("Yasuo").unwrap().id);
+    assert_eq!(157, registry.find_by_name("yASUO").unwrap().id);
+    assert_eq!(36, registry.find_by_name("dr. mundo").unwrap().id);
+    assert_eq!(36, registry.find_by_name("mundo").unwrap().id);
+    assert_eq!(121, registry.find_by_name("khazix").unwrap().id);
+    assert_eq!(62, registry.find_by_name("MonkeyKing").unwrap().id);
+    assert_eq!(157, registry.find_by_name("Yasou").unwrap().id);
+}
+
+#[test]
+fn test_find_by_name_suggestions() {
+    let registry = ChampionRegistry::from_static_data(STATIC_DATA).unwrap();
+    let suggestions = registry.find_by_name("an").unwrap_err();
+    assert!(suggestions.len() > 1);
+    assert!(suggestions.iter().all(|champion| champion.name.to_lowercase().contains("an")
+                                   || champion.key.to_lowercase().contains("an")));
+    assert!(registry.find_by_name("zzzzzzzzzz").unwrap_err().is_empty());
+    assert!(registry.find_by_name("").is_err());
+}
+
+#[test]
+fn test_invalid_static_data() {
+    assert!(ChampionRegistry::from_static_data("not json").is_err());
+    assert!(ChampionRegistry::from_static_data(r#"{"type": "champion"}"#).is_err());
+    assert!(ChampionRegistry::from_static_data(r#"{"data": {"1": {"id": 1}}}"#).is_err());
+}
+
+#[test]
+fn test_serialize() {
+    let registry = ChampionRegistry::from_static_data(STATIC_DATA).unwrap();
+    let serialized = registry.serialize().unwrap();
+    assert_eq!(registry, ChampionRegistry::from_static_data(&serialized).unwrap());
+}
@@ -0,0 +1,116 @@
+extern crate zilean;
+
+use zilean::champion::Champion as Champion;
+use zilean::corpus::Entry as Entry;
+use zilean::database::Database as Database;
+use zilean::registry::ChampionRegistry as ChampionRegistry;
+use zilean::snapshot::*;
+use std::env;
+use std::fs;
+use std::process;
+
+fn staging() -> Database {
+    let db = Database::new("memory://".to_string());
+    let registry = ChampionRegistry::from_static_data(include_str!("../../twitch/dbg_champs.json")).unwrap();
+    db.store_registry(&registry).unwrap();
+    db.feed(10, &[Entry::new("foo".to_string()), Entry::new("bar".to_string())]).unwrap();
+    db.feed(11, &[Entry::new("baz".to_string())]).unwrap();
+    db
+}
+
+#[test]
+fn test_export_import() {
+    let staging = staging();
+    let mut file = Vec::new();
+    let stats = export(&staging, &mut file).unwrap();
+    assert_eq!(SnapshotStats { champions : 2, nicknames : 3, registry : true }, stats);
+
+    let production = Database::new("memory://".to_string());
+    assert_eq!(stats, import(&production, &file[..], ImportMode::Replace).unwrap());
+    assert_eq!(vec![10, 11], production.list().unwrap());
+    assert_eq!(staging.load(10).unwrap(), production.load(10).unwrap());
+    assert_eq!(staging.load(11).unwrap(), production.load(11).unwrap());
+    assert_eq!(2, production.load_corpus(10).len());
+    assert_eq!(staging.load_corpus(11).get("baz"), production.load_corpus(11).get("baz"));
+    assert_eq!(staging.load_registry(), production.load_registry());
+}
+
+#[test]
+fn test_export_deleted() {
+    //A champion listed in the index, but deleted before it could be loaded.
+    let dir = env::temp_dir().join(format!("zilean-test-export-deleted-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let db = Database::new(format!("file://{}", dir.display()));
+    db.feed(10, &[Entry::new("foo".to_string())]).unwrap();
+    db.feed(11, &[Entry::new("bar".to_string())]).unwrap();
+    fs::remove_file(dir.join("champions").join("10.json")).unwrap();
+
+    let mut file = Vec::new();
+    assert_eq!(1, export(&db, &mut file).unwrap().champions);
+    let production = Database::new("memory://".to_string());
+    import(&production, &file[..], ImportMode::Replace).unwrap();
+    assert_eq!(vec![11], production.list().unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_import_modes() {
+    let mut file = Vec::new();
+    export(&staging(), &mut file).unwrap();
+
+    let db = Database::new("memory://".to_string());
+    let mut champion = Champion::new(10);
+    champion.feed("qux");
+    db.store(&champion).unwrap();
+    db.add_to_corpus(10, &[Entry::new("qux".to_string())]).unwrap();
+    db.feed(12, &[Entry::new("quux".to_string())]).unwrap();
+
+    //Merging replaces champion 10, keeps champion 12, and merges corpora.
+    import(&db, &file[..], ImportMode::Merge).unwrap();
+    assert_eq!(vec![10, 11, 12], db.list().unwrap());
+    assert!(db.load(10).unwrap().unwrap() != champion);
+    assert_eq!(3, db.load_corpus(10).len());
+
+    //Replacing deletes champion 12, and keeps merged corpora.
+    import(&db, &file[..], ImportMode::Replace).unwrap();
+    assert_eq!(vec![10, 11], db.list().unwrap());
+    assert_eq!(staging().load(10).unwrap(), db.load(10).unwrap());
+    assert_eq!(3, db.load_corpus(10).len());
+}
+
+#[test]
+fn test_import_invalid() {
+    let db = Database::new("memory://".to_string());
+    db.feed(10, &[Entry::new("foo".to_string())]).unwrap();
+
+    let newer = r#"{"manifest": {"format": "zelkow-snapshot", "version": 99}, "registry": null, "champions": []}"#;
+    match import(&db, newer.as_bytes(), ImportMode::Replace) {
+        Err(SnapshotError::UnsupportedVersion(99)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    let other = r#"{"champions": []}"#;
+    match import(&db, other.as_bytes(), ImportMode::Replace) {
+        Err(SnapshotError::Invalid(_)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    let corrupt = r#"{"manifest": {"format": "zelkow-snapshot", "version": 1}, "registry": null,
+                      "champions": [{"id": 11, "chain": {"order": 0}, "corpus": {}}]}"#;
+    match import(&db, corrupt.as_bytes(), ImportMode::Replace) {
+        Err(SnapshotError::Invalid(_)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    let wrapping = r#"{"manifest": {"format": "zelkow-snapshot", "version": 1}, "registry": null,
+                       "champions": [{"id": 4294967306, "chain": {}, "corpus": {}}]}"#;
+    match import(&db, wrapping.as_bytes(), ImportMode::Merge) {
+        Err(SnapshotError::Invalid(_)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    let repeated = r#"{"manifest": {"format": "zelkow-snapshot", "version": 1}, "registry": null,
+                       "champions": [{"id": 11, "chain": {}, "corpus": {}}, {"id": 11, "chain": {}, "corpus": {}}]}"#;
+    match import(&db, repeated.as_bytes(), ImportMode::Merge) {
+        Err(SnapshotError::Invalid(_)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    //Invalid snapshots leave the database untouched.
+    assert_eq!(vec![10], db.list().unwrap());
+}
@@ -0,0 +1,130 @@
+extern crate zilean;
+
+use zilean::champion::Champion as Champion;
+use zilean::corpus::Entry as Entry;
+use zilean::database::Database as Database;
+use zilean::store::redis_pool::PoolConfig as PoolConfig;
+use zilean::store::redis_store::{RedisConfig, DEFAULT_NAMESPACE};
+use std::env;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+fn assert_send_sync<T : Send + Sync>() {}
+
+#[test]
+fn test_send_sync() {
+    assert_send_sync::<Database>();
+}
+
+#[test]
+fn test_shared_database() {
+    let db = Arc::new(Database::new("memory://".to_string()));
+    let threads : Vec<_> = (0..4).map(|i| {
+        let db = db.clone();
+        thread::spawn(move || {
+            db.feed(30 + i, &[Entry::new("foo".to_string())]).unwrap();
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(vec![30, 31, 32, 33], db.list().unwrap());
+}
+
+#[test]
+fn test_redis_config_from_url() {
+    let (url, config) = RedisConfig::from_url("redis://127.0.0.1").unwrap();
+    assert_eq!("redis://127.0.0.1", url);
+    assert_eq!(RedisConfig::default(), config);
+    assert_eq!(DEFAULT_NAMESPACE, config.namespace);
+
+    let (url, config) = RedisConfig::from_url("redis://127.0.0.1/?pool_size=2&db=3&namespace=&backoff_ms=10").unwrap();
+    assert_eq!("redis://127.0.0.1/?db=3", url);
+    assert_eq!("", config.namespace);
+    assert_eq!(2, config.pool.max_idle);
+    assert_eq!(Duration::from_millis(10), config.pool.backoff);
+
+    assert!(RedisConfig::from_url("redis://127.0.0.1/?retries=many").is_err());
+}
+
+#[test]
+fn test_pool_delay() {
+    let config = PoolConfig {
+        max_idle : 1,
+        retries : 3,
+        backoff : Duration::from_millis(50),
+        max_backoff : Duration::from_millis(300),
+    };
+    assert_eq!(Duration::from_millis(50), config.delay(0));
+    assert_eq!(Duration::from_millis(100), config.delay(1));
+    assert_eq!(Duration::from_millis(300), config.delay(3));
+    assert_eq!(Duration::from_millis(300), config.delay(40));
+}
+
+//The tests below need a Redis instance : they are run with `cargo test -- --ignored`, against REDIS_URL if it is set,
+//like REDIS_URL=redis://redis, or a local instance otherwise.
+fn redis_database() -> Database {
+    let url = env::var("REDIS_URL").unwrap_or("redis://127.0.0.1".to_string());
+    Database::new(format!("{}/?namespace=zilean-test", url.trim_right_matches('/')))
+}
+
+#[test]
+#[ignore]
+fn test_redis_concurrent_feeds() {
+    let db = Arc::new(redis_database());
+    db.delete(40).unwrap();
+    let threads : Vec<_> = (0..8).map(|i| {
+        let db = db.clone();
+        thread::spawn(move || {
+            (0..10).map(|j| {
+                db.feed(40, &[Entry::new(format!("foo{}{}", i, j)), Entry::new("bar".to_string())]).unwrap()
+            }).sum::<usize>()
+        })
+    }).collect();
+    let fed : usize = threads.into_iter().map(|thread| thread.join().unwrap()).sum();
+
+    //Every thread fed "bar", it is only counted once. The other nicknames each added their transitions once.
+    let mut expected = Champion::new(40);
+    expected.feed_all((0..8).flat_map(|i| (0..10).map(move |j| format!("foo{}{}", i, j))));
+    expected.feed("bar");
+    assert_eq!(81, fed);
+    assert_eq!(Some(expected), db.load(40).unwrap());
+    assert_eq!(81, db.load_corpus(40).len());
+    db.delete(40).unwrap();
+}
+
+#[test]
+#[ignore]
+fn test_redis_compare_and_store_conflicts() {
+    let db = Arc::new(redis_database());
+    db.delete(41).unwrap();
+    db.store(&Champion::new(41)).unwrap();
+
+    //Written since it was loaded.
+    let loaded = db.load(41).unwrap();
+    db.feed(41, &[Entry::new("bar".to_string())]).unwrap();
+    assert!(!db.compare_and_store(loaded.as_ref(), &Champion::new(41)).unwrap());
+    assert_eq!(1, db.load(41).unwrap().unwrap().stats().nicknames);
+
+    //Threads racing to update the champion : those losing the race get `false`, and no update is overwritten.
+    let threads : Vec<_> = (0..8).map(|i| {
+        let db = db.clone();
+        thread::spawn(move || {
+            let mut stored = 0;
+            for j in 0..10 {
+                let current = db.load(41).unwrap().unwrap();
+                let mut champion = current.clone();
+                champion.feed(format!("foo{}{}", i, j));
+                if db.compare_and_store(Some(&current), &champion).unwrap() {
+                    stored += 1;
+                }
+            }
+            stored
+        })
+    }).collect();
+    let stored : u64 = threads.into_iter().map(|thread| thread.join().unwrap()).sum();
+    assert!(stored > 0);
+    assert_eq!(1 + stored, db.load(41).unwrap().unwrap().stats().nicknames);
+    db.delete(41).unwrap();
+}
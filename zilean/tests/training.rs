@@ -0,0 +1,36 @@
+extern crate zilean;
+
+use zilean::champion::Champion as Champion;
+use zilean::database::Database as Database;
+use zilean::training::train_parallel;
+
+fn jobs() -> Vec<(Champion, Vec<String>)> {
+    Database::deserialize_bulk(include_str!("../db.json").to_string()).unwrap().into_iter()
+        .map(|(champion_id, nicknames)| (Champion::with_order(champion_id, 2), nicknames))
+        .collect()
+}
+
+#[test]
+fn test_train_parallel() {
+    let mut expected : Vec<Champion> = jobs().into_iter().map(|(mut champion, nicknames)| {
+        champion.feed_all(&nicknames);
+        champion
+    }).collect();
+    expected.sort_by_key(|champion| champion.get_id());
+
+    assert_eq!(expected, train_parallel(jobs(), 1));
+    assert_eq!(expected, train_parallel(jobs(), 4));
+    assert_eq!(expected, train_parallel(jobs(), 0));
+}
+
+#[test]
+fn test_train_parallel_trained() {
+    let mut champion = Champion::new(10);
+    champion.feed("foo");
+    let champions = train_parallel(vec![(champion, vec!["bar".to_string()])], 8);
+
+    let mut expected = Champion::new(10);
+    expected.feed_all(vec!["foo", "bar"]);
+    assert_eq!(vec![expected], champions);
+    assert!(train_parallel(Vec::new(), 8).is_empty());
+}